@@ -0,0 +1,42 @@
+extern crate file_minidb;
+
+use file_minidb::binary::BinarySerializable;
+use file_minidb::serializer::Serializable;
+use file_minidb::table::Table;
+
+use std::time::{Duration, Instant};
+
+#[path = "../src/test_data.rs"]
+mod test_data;
+
+const ROWS: usize = 100_000;
+const RUNS: u32 = 5;
+
+fn measure<T, F: FnMut() -> T>(name: &str, mut f: F) {
+    let mut total = Duration::new(0, 0);
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        std::hint::black_box(f());
+        total += start.elapsed();
+    }
+    println!("{:<20} {:>10.2?}", name, total / RUNS);
+}
+
+fn main() {
+    let table = test_data::create_large_table(ROWS);
+
+    let text = table.serialize();
+    let binary = table.serialize_binary();
+    println!("{} rows, average of {} runs", ROWS, RUNS);
+    println!("{:<20} {:>10} bytes", "text size", text.len());
+    println!("{:<20} {:>10} bytes", "binary size", binary.len());
+
+    measure("text serialize", || table.serialize());
+    measure("binary serialize", || table.serialize_binary());
+    measure("text deserialize", || {
+        Table::deserialize(text.clone()).unwrap()
+    });
+    measure("binary deserialize", || {
+        Table::deserialize_binary(&binary).unwrap()
+    });
+}
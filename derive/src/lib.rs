@@ -0,0 +1,106 @@
+//! The `Row` derive macro of file-minidb, used through its `derive` feature.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields};
+
+/// Implements `file_minidb::row::Row` for a struct with named fields.
+///
+/// Every field is a column named like the field, without `r#` for raw
+/// identifiers, in the order of the fields.
+/// Fields marked with `#[key]` are key columns. The types of the fields must
+/// implement `file_minidb::row::ColumnValue`.
+#[proc_macro_derive(Row, attributes(key))]
+pub fn derive_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    input,
+                    "Row can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                input,
+                "Row can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut columns = vec![];
+    let mut to_values = vec![];
+    let mut from_values = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let name = ident.unraw().to_string();
+
+        let mut is_key = false;
+        for attribute in &field.attrs {
+            if attribute.path().is_ident("key") {
+                attribute.meta.require_path_only()?;
+                is_key = true;
+            }
+        }
+        let constructor = if is_key { quote!(key) } else { quote!(new) };
+
+        columns.push(quote! {
+            ::file_minidb::column::Column::#constructor(
+                #name,
+                <#ty as ::file_minidb::row::ColumnValue>::column_type(),
+            )
+        });
+        to_values.push(quote! {
+            ::file_minidb::row::ColumnValue::to_value(&self.#ident)
+        });
+        from_values.push(quote! {
+            #ident: ::file_minidb::row::ColumnValue::from_value(
+                values.next().ok_or_else(|| ::std::format!("Missing value for column {}", #name))?,
+            ).map_err(|e| ::std::format!("Column {}: {}", #name, e))?
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::file_minidb::row::Row for #ident #type_generics #where_clause {
+            fn schema() -> ::std::vec::Vec<::file_minidb::column::Column> {
+                ::std::vec![#(#columns),*]
+            }
+
+            fn to_values(&self) -> ::std::vec::Vec<::file_minidb::values::Value> {
+                ::std::vec![#(#to_values),*]
+            }
+
+            fn from_values(
+                values: ::std::vec::Vec<::file_minidb::values::Value>,
+            ) -> ::std::result::Result<Self, ::std::string::String> {
+                let mut values = values.into_iter();
+                let row = #ident {
+                    #(#from_values),*
+                };
+                if values.next().is_some() {
+                    return ::std::result::Result::Err(::std::string::String::from(
+                        "More values than columns",
+                    ));
+                }
+                ::std::result::Result::Ok(row)
+            }
+        }
+    })
+}
@@ -21,7 +21,7 @@ pub fn main() {
         if parts.len() != 2 {
             println!("Exactly two inputs have to be given. Try again.");
         } else {
-            let name = parts.get(0).unwrap();
+            let name = parts.first().unwrap();
             let age_str = parts.get(1).unwrap();
 
             let age_opt = age_str.parse::<i32>();
@@ -34,14 +34,12 @@ pub fn main() {
                 } else {
                     let entries = table.get_entries();
 
-                    println!("");
+                    println!();
                     for entry in entries {
-                        let values = entry.get_values();
-
                         println!(
                             "{} is {} years old.",
-                            values.get(0).unwrap(),
-                            values.get(1).unwrap()
+                            entry.get_str("Name").unwrap().unwrap_or("Nobody"),
+                            entry.get_i32("Age").unwrap().unwrap_or(0)
                         );
                     }
                 }
@@ -49,8 +47,8 @@ pub fn main() {
                 println!("Cannot parse a number from the second argument");
             }
         }
-        println!("");
-        println!("");
+        println!();
+        println!();
         println!("Please input name and age.");
     }
 }
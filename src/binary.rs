@@ -0,0 +1,277 @@
+use crate::column::Column;
+use crate::file;
+use crate::table::Table;
+use crate::types::ColumnType;
+use crate::values::Value;
+
+use std::path::Path;
+
+/// The first bytes of every table in the binary format.
+pub const MAGIC: &[u8] = b"FMDB";
+
+const TYPE_STRING: u8 = 0;
+const TYPE_INTEGER: u8 = 1;
+
+const FLAG_KEY: u8 = 1;
+
+const VALUE_NULL: u8 = 0;
+const VALUE_PRESENT: u8 = 1;
+
+/// Objects that can be stored in the compact binary format.
+///
+/// A table is stored as `MAGIC`, followed by the schema block and the entries.
+/// The schema block is the number of columns and for every column its flags,
+/// type and name. The entries are their number followed by every value of
+/// every entry. Numbers are stored as varints, integer values zigzag encoded,
+/// and strings are prefixed by their length in bytes.
+pub trait BinarySerializable {
+    fn serialize_binary(&self) -> Vec<u8>;
+}
+
+impl BinarySerializable for Table {
+    fn serialize_binary(&self) -> Vec<u8> {
+        let mut result = MAGIC.to_vec();
+
+        write_varint(&mut result, self.columns.len() as u64);
+        for column in &self.columns {
+            result.push(if column.is_key { FLAG_KEY } else { 0 });
+            result.push(match column.column_type {
+                ColumnType::String => TYPE_STRING,
+                ColumnType::Integer => TYPE_INTEGER,
+            });
+            write_string(&mut result, &column.name);
+        }
+
+        write_varint(&mut result, self.entries.len() as u64);
+        for entry in &self.entries {
+            for (_, value) in &entry.values {
+                match value {
+                    Value::Null(_) => result.push(VALUE_NULL),
+                    Value::String(s) => {
+                        result.push(VALUE_PRESENT);
+                        write_string(&mut result, s);
+                    }
+                    Value::Integer(i) => {
+                        result.push(VALUE_PRESENT);
+                        write_varint(&mut result, zigzag(*i));
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
+impl Table {
+    /// Deserialize a table in the binary format, see `BinarySerializable`.
+    pub fn deserialize_binary(bytes: &[u8]) -> Result<Table, String> {
+        let mut reader = Reader::new(bytes);
+        if reader.read_bytes(MAGIC.len())? != MAGIC {
+            return Err("Not a binary table".to_string());
+        }
+
+        let mut columns = vec![];
+        for _ in 0..reader.read_varint()? {
+            let is_key = match reader.read_byte()? {
+                0 => false,
+                FLAG_KEY => true,
+                flags => return Err(format!("Invalid column flags: {}", flags)),
+            };
+            let column_type = match reader.read_byte()? {
+                TYPE_STRING => ColumnType::String,
+                TYPE_INTEGER => ColumnType::Integer,
+                t => return Err(format!("Invalid column type: {}", t)),
+            };
+            columns.push(Column {
+                is_key,
+                name: reader.read_string()?,
+                column_type,
+            });
+        }
+
+        let mut table = Table::new(columns)?;
+
+        let mut entries = vec![];
+        for _ in 0..reader.read_varint()? {
+            let mut values = Vec::with_capacity(table.columns.len());
+            for column in &table.columns {
+                let value = match (reader.read_byte()?, &column.column_type) {
+                    (VALUE_NULL, t) => Value::Null(t.clone()),
+                    (VALUE_PRESENT, ColumnType::String) => Value::String(reader.read_string()?),
+                    (VALUE_PRESENT, ColumnType::Integer) => {
+                        Value::Integer(unzigzag(reader.read_varint()?)?)
+                    }
+                    (tag, _) => return Err(format!("Invalid value tag: {}", tag)),
+                };
+                values.push(value);
+            }
+            entries.push(values);
+        }
+        table.insert_all(entries)?;
+
+        if !reader.is_at_end() {
+            return Err("Unexpected data after the table".to_string());
+        }
+
+        Ok(table)
+    }
+
+    /// Write the table to a file in the binary format.
+    /// Will atomically replace the file, like `write_file`.
+    pub fn write_binary_file(&self, path: &Path) -> Result<(), String> {
+        file::write_atomic(path, &self.serialize_binary())
+    }
+}
+
+/// If the bytes are in the binary format and not in the text format.
+pub(crate) fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn write_varint(result: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        result.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    result.push(value as u8);
+}
+
+fn write_string(result: &mut Vec<u8>, str: &str) {
+    write_varint(result, str.len() as u64);
+    result.extend_from_slice(str.as_bytes());
+}
+
+// Maps integers with a small absolute value to small varints.
+fn zigzag(value: i32) -> u64 {
+    ((value << 1) ^ (value >> 31)) as u32 as u64
+}
+
+fn unzigzag(value: u64) -> Result<i32, String> {
+    if value > u32::MAX as u64 {
+        return Err(format!("Integer out of range: {}", value));
+    }
+    let value = value as u32;
+    Ok(((value >> 1) as i32) ^ -((value & 1) as i32))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, position: 0 }
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() - self.position < length {
+            return Err("Unexpected end of data".to_string());
+        }
+        let bytes = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, String> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Varint too long".to_string())
+    }
+
+    fn read_string(&mut self) -> Result<String, String> {
+        let length = self.read_varint()?;
+        if length > (self.bytes.len() - self.position) as u64 {
+            return Err("Unexpected end of data".to_string());
+        }
+        let bytes = self.read_bytes(length as usize)?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{unzigzag, zigzag, BinarySerializable};
+    use crate::column::Column;
+    use crate::serializer::Serializable;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    fn create_test_table() -> Table {
+        let mut table = Table::new(vec![
+            Column::key("Name, First", ColumnType::String),
+            Column::new("Age", ColumnType::Integer),
+        ])
+        .unwrap();
+        table.insert(vec!["Peter".into(), 15.into()]).unwrap();
+        table
+            .insert(vec!["Wendy \"Moira\"".into(), i32::MIN.into()])
+            .unwrap();
+        table
+            .insert(vec!["".into(), Value::Null(ColumnType::Integer)])
+            .unwrap();
+        table
+    }
+
+    #[test]
+    fn zigzag_roundtrip() {
+        for i in [0, 1, -1, 63, -64, i32::MAX, i32::MIN] {
+            assert_eq!(unzigzag(zigzag(i)), Ok(i));
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let table = create_test_table();
+        let bytes = table.serialize_binary();
+
+        assert_eq!(Table::deserialize_binary(&bytes), Ok(table.clone()));
+        assert!(bytes.len() < table.serialize().len());
+    }
+
+    #[test]
+    fn invalid_data() {
+        let bytes = create_test_table().serialize_binary();
+
+        assert!(Table::deserialize_binary(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Table::deserialize_binary(&bytes[1..]).is_err());
+
+        let mut longer = bytes;
+        longer.push(0);
+        assert!(Table::deserialize_binary(&longer).is_err());
+    }
+
+    #[test]
+    fn from_file_detects_format() {
+        let path =
+            std::env::temp_dir().join(format!("file-minidb-binary-{}.table", std::process::id()));
+        let table = create_test_table();
+
+        table.write_binary_file(&path).unwrap();
+        assert_eq!(Table::from_file(&path), Ok(table.clone()));
+
+        table.write_file(&path).unwrap();
+        assert_eq!(Table::from_file(&path), Ok(table));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 use crate::types::ColumnType;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Column {
     pub(crate) is_key: bool,
     pub(crate) name: String,
@@ -0,0 +1,122 @@
+#[cfg(feature = "compression")]
+use crate::file;
+#[cfg(feature = "compression")]
+use crate::table::Table;
+
+#[cfg(feature = "compression")]
+use std::io::{Read, Write};
+#[cfg(feature = "compression")]
+use std::path::Path;
+
+/// The first bytes of a compressed table file, which is a gzip stream.
+pub const MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// If the bytes are a compressed container, see `Table::write_compressed_file`.
+pub(crate) fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+#[cfg(feature = "compression")]
+pub(crate) fn decompress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut result = vec![];
+    flate2::read::GzDecoder::new(bytes)
+        .read_to_end(&mut result)
+        .map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn decompress(_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    Err("Reading compressed tables needs the compression feature".to_string())
+}
+
+#[cfg(feature = "compression")]
+pub(crate) fn compress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    encoder.write_all(bytes).map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn compress(_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    Err("Writing compressed tables needs the compression feature".to_string())
+}
+
+#[cfg(feature = "compression")]
+impl Table {
+    /// Write the table to a file like `write_file`, but compressed with gzip.
+    /// `from_file` detects and reads compressed files.
+    pub fn write_compressed_file(&self, path: &Path) -> Result<(), String> {
+        file::write_atomic(path, &compress(self.serialize_current().as_bytes())?)
+    }
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod test {
+
+    use super::{compress, MAGIC};
+    use crate::table::Table;
+    use crate::test_data::create_large_table;
+
+    use std::fs;
+    use std::time::Instant;
+
+    #[test]
+    fn compare_with_plain_file() {
+        let directory = std::env::temp_dir();
+        let plain = directory.join(format!("file-minidb-plain-{}.table", std::process::id()));
+        let compressed = directory.join(format!(
+            "file-minidb-compressed-{}.table",
+            std::process::id()
+        ));
+        let table = create_large_table(100_000);
+
+        let start = Instant::now();
+        table.write_file(&plain).unwrap();
+        let plain_write = start.elapsed();
+        let start = Instant::now();
+        table.write_compressed_file(&compressed).unwrap();
+        let compressed_write = start.elapsed();
+
+        let start = Instant::now();
+        assert_eq!(Table::from_file(&plain).unwrap(), table);
+        let plain_read = start.elapsed();
+        let start = Instant::now();
+        assert_eq!(Table::from_file(&compressed).unwrap(), table);
+        let compressed_read = start.elapsed();
+
+        let plain_size = fs::metadata(&plain).unwrap().len();
+        let compressed_size = fs::metadata(&compressed).unwrap().len();
+        println!(
+            "plain: {} bytes, write {:?}, read {:?}",
+            plain_size, plain_write, plain_read
+        );
+        println!(
+            "compressed: {} bytes, write {:?}, read {:?}",
+            compressed_size, compressed_write, compressed_read
+        );
+        assert!(compressed_size * 3 < plain_size);
+
+        fs::remove_file(&plain).unwrap();
+        fs::remove_file(&compressed).unwrap();
+    }
+
+    #[test]
+    fn migrate_compressed_file() {
+        let path = std::env::temp_dir().join(format!(
+            "file-minidb-compressed-migrate-{}.table",
+            std::process::id()
+        ));
+        let mut table = Table::deserialize(r#"key int "Id",str "Name""#.to_string()).unwrap();
+        table.insert(vec![1.into(), "Peter".into()]).unwrap();
+
+        let old = table.serialize_version(1).unwrap();
+        fs::write(&path, compress(old.as_bytes()).unwrap()).unwrap();
+        assert_eq!(Table::migrate_file(&path), Ok(true));
+        assert!(fs::read(&path).unwrap().starts_with(MAGIC));
+        assert_eq!(Table::from_file(&path), Ok(table));
+        assert_eq!(Table::migrate_file(&path), Ok(false));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,427 @@
+use crate::column::Column;
+use crate::table::Table;
+use crate::types::ColumnType;
+use crate::values::Value;
+
+use std::collections::HashMap;
+
+/// How a table is read from and written to CSV, see `Table::from_csv`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub(crate) delimiter: char,
+    pub(crate) types: HashMap<String, ColumnType>,
+    pub(crate) infer_types: bool,
+    pub(crate) keys: Vec<String>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions::new()
+    }
+}
+
+impl CsvOptions {
+    /// Comma separated, all columns are strings and none are keys.
+    pub fn new() -> CsvOptions {
+        CsvOptions {
+            delimiter: ',',
+            types: HashMap::new(),
+            infer_types: false,
+            keys: vec![],
+        }
+    }
+
+    /// Sets the character between fields. Quotes and line breaks cannot be
+    /// delimiters, `to_csv` and `from_csv` will error for them.
+    pub fn delimiter(mut self, delimiter: char) -> CsvOptions {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets the type of a column on import.
+    pub fn column_type<T: AsRef<str>>(mut self, name: T, column_type: ColumnType) -> CsvOptions {
+        self.types.insert(name.as_ref().to_string(), column_type);
+        self
+    }
+
+    /// On import, columns without a type are integer columns if all their
+    /// values are integers, and string columns otherwise.
+    pub fn infer_types(mut self, infer_types: bool) -> CsvOptions {
+        self.infer_types = infer_types;
+        self
+    }
+
+    /// Sets the key columns on import.
+    pub fn keys(mut self, names: &[&str]) -> CsvOptions {
+        self.keys = names.iter().map(|n| n.to_string()).collect();
+        self
+    }
+
+    fn check_delimiter(&self) -> Result<(), String> {
+        match self.delimiter {
+            '"' | '\r' | '\n' => Err(format!("Invalid delimiter: {:?}", self.delimiter)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Table {
+    /// Writes the table as CSV following RFC 4180, with a header row of the
+    /// column names and CRLF line breaks.
+    ///
+    /// Fields are quoted if they contain the delimiter, a quote or a line
+    /// break. Null values are written as empty fields and empty strings as
+    /// `""`, so that they can be told apart on import.
+    /// Will error if the delimiter is a quote or a line break, and if the
+    /// table has one column and the last entry is null, which would be
+    /// written as an empty line at the end and is not read as a row.
+    pub fn to_csv(&self, options: &CsvOptions) -> Result<String, String> {
+        options.check_delimiter()?;
+        if let Some(entry) = self.entries.last() {
+            if let [(_, Value::Null(_))] = entry.values.as_slice() {
+                return Err("Null in the last row of a single column cannot be written".to_string());
+            }
+        }
+
+        let mut result = String::new();
+        let names: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| quote(&c.name, options.delimiter))
+            .collect();
+        push_row(&mut result, &names, options.delimiter);
+
+        for entry in &self.entries {
+            let fields: Vec<String> = entry
+                .values
+                .iter()
+                .map(|(_, value)| match value {
+                    Value::Null(_) => String::new(),
+                    Value::String(s) if s.is_empty() => "\"\"".to_string(),
+                    Value::String(s) => quote(s, options.delimiter),
+                    Value::Integer(i) => i.to_string(),
+                })
+                .collect();
+            push_row(&mut result, &fields, options.delimiter);
+        }
+
+        Ok(result)
+    }
+
+    /// Reads a table from CSV following RFC 4180, which must start with a
+    /// header row of the column names. Both CRLF and LF line breaks are read.
+    ///
+    /// The types and keys of the columns are taken from the options. Empty
+    /// fields are null, unless they are quoted in a string column.
+    /// Will error if the delimiter is a quote or a line break.
+    pub fn from_csv(str: &str, options: &CsvOptions) -> Result<Table, String> {
+        options.check_delimiter()?;
+
+        let mut rows = parse(str, options.delimiter)?.into_iter();
+        let names: Vec<String> = rows
+            .next()
+            .ok_or_else(|| "CSV has no header row".to_string())?
+            .into_iter()
+            .map(|f| f.text)
+            .collect();
+        let rows: Vec<Vec<Field>> = rows.collect();
+
+        for (index, row) in rows.iter().enumerate() {
+            if row.len() != names.len() {
+                return Err(format!(
+                    "Row {} has {} fields, expected {}",
+                    index + 2,
+                    row.len(),
+                    names.len()
+                ));
+            }
+        }
+        for name in options.types.keys().chain(&options.keys) {
+            if !names.contains(name) {
+                return Err(format!("Unknown column: {}", name));
+            }
+        }
+
+        let columns: Vec<Column> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| Column {
+                is_key: options.keys.contains(name),
+                name: name.clone(),
+                column_type: match options.types.get(name) {
+                    Some(column_type) => column_type.clone(),
+                    None if options.infer_types && is_integer_column(&rows, index) => {
+                        ColumnType::Integer
+                    }
+                    None => ColumnType::String,
+                },
+            })
+            .collect();
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for (index, row) in rows.into_iter().enumerate() {
+            let mut values = Vec::with_capacity(columns.len());
+            for (field, column) in row.into_iter().zip(&columns) {
+                let value = match column.column_type {
+                    _ if field.text.is_empty() && !field.is_quoted => {
+                        Value::Null(column.get_type())
+                    }
+                    ColumnType::String => Value::String(field.text),
+                    ColumnType::Integer if field.text.is_empty() => {
+                        Value::Null(ColumnType::Integer)
+                    }
+                    ColumnType::Integer => Value::Integer(field.text.parse().map_err(|_| {
+                        format!(
+                            "Cannot parse integer in row {}, column {}: {}",
+                            index + 2,
+                            column.name,
+                            field.text
+                        )
+                    })?),
+                };
+                values.push(value);
+            }
+            entries.push(values);
+        }
+
+        let mut table = Table::new(columns)?;
+        table.insert_all(entries)?;
+        Ok(table)
+    }
+}
+
+// A parsed field, quoted fields can be empty strings.
+#[derive(Default)]
+struct Field {
+    text: String,
+    is_quoted: bool,
+}
+
+fn quote(str: &str, delimiter: char) -> String {
+    if str.contains([delimiter, '"', '\r', '\n']) {
+        format!("\"{}\"", str.replace('"', "\"\""))
+    } else {
+        str.to_string()
+    }
+}
+
+fn push_row(result: &mut String, fields: &[String], delimiter: char) {
+    result.push_str(&fields.join(&delimiter.to_string()));
+    result.push_str("\r\n");
+}
+
+fn is_integer_column(rows: &[Vec<Field>], index: usize) -> bool {
+    let mut values = rows
+        .iter()
+        .map(|r| &r[index].text)
+        .filter(|t| !t.is_empty())
+        .peekable();
+    values.peek().is_some() && values.all(|t| t.parse::<i32>().is_ok())
+}
+
+fn parse(str: &str, delimiter: char) -> Result<Vec<Vec<Field>>, String> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = Field::default();
+    let mut chars = str.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.text.is_empty() && !field.is_quoted => {
+                field.is_quoted = true;
+                let start_line = line;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.text.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            field.text.push(c);
+                        }
+                        None => {
+                            return Err(format!("Unclosed quote starting in line {}", start_line))
+                        }
+                    }
+                }
+                match chars.peek() {
+                    Some(c) if *c == delimiter || *c == '\r' || *c == '\n' => {}
+                    None => {}
+                    Some(_) => return Err(format!("Text after closing quote in line {}", line)),
+                }
+            }
+            '"' => return Err(format!("Quote in unquoted field in line {}", line)),
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                line += 1;
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.text.push(c),
+        }
+    }
+
+    // The last line break is optional.
+    if !row.is_empty() || !field.text.is_empty() || field.is_quoted {
+        row.push(field);
+        rows.push(row);
+    }
+    // An empty line after the last line break is not a row. Empty lines
+    // before it are rows with a null in tables with one column.
+    if let Some([last]) = rows.last().map(|r| r.as_slice()) {
+        if last.text.is_empty() && !last.is_quoted {
+            rows.pop();
+        }
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::CsvOptions;
+    use crate::column::Column;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    fn create_test_table() -> Table {
+        let mut table = Table::new(vec![
+            Column::key("Name", ColumnType::String),
+            Column::new("Age", ColumnType::Integer),
+            Column::new("Note", ColumnType::String),
+        ])
+        .unwrap();
+        table
+            .insert(vec!["Peter".into(), 15.into(), "Says \"hi\", often".into()])
+            .unwrap();
+        table
+            .insert(vec![
+                "Wendy".into(),
+                Value::Null(ColumnType::Integer),
+                "".into(),
+            ])
+            .unwrap();
+        table
+            .insert(vec![
+                "John".into(),
+                (-10).into(),
+                Value::Null(ColumnType::String),
+            ])
+            .unwrap();
+        table
+            .insert(vec!["Michael".into(), 8.into(), "two\nlines".into()])
+            .unwrap();
+        table
+    }
+
+    #[test]
+    fn to_csv() {
+        let csv = create_test_table().to_csv(&CsvOptions::new()).unwrap();
+        assert_eq!(
+            csv,
+            "Name,Age,Note\r\nPeter,15,\"Says \"\"hi\"\", often\"\r\nWendy,,\"\"\r\nJohn,-10,\r\nMichael,8,\"two\nlines\"\r\n"
+        );
+
+        let csv = create_test_table()
+            .to_csv(&CsvOptions::new().delimiter(';'))
+            .unwrap();
+        assert!(csv.starts_with("Name;Age;Note\r\nPeter;15;\"Says \"\"hi\"\", often\"\r\n"));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let table = create_test_table();
+        for delimiter in [',', ';', '\t'] {
+            let options = CsvOptions::new()
+                .delimiter(delimiter)
+                .column_type("Age", ColumnType::Integer)
+                .keys(&["Name"]);
+            assert_eq!(
+                Table::from_csv(&table.to_csv(&options).unwrap(), &options),
+                Ok(table.clone())
+            );
+
+            let inferred = options.clone().infer_types(true);
+            assert_eq!(
+                Table::from_csv(&table.to_csv(&options).unwrap(), &inferred),
+                Ok(table.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn infer_types() {
+        let csv = "Id,Name,Code,Empty\n1,Peter,7,\n-2,15,x7,\n3,,,\n";
+        let table = Table::from_csv(csv, &CsvOptions::new().infer_types(true)).unwrap();
+        let types: Vec<ColumnType> = table.columns.iter().map(|c| c.get_type()).collect();
+        assert_eq!(
+            types,
+            vec![
+                ColumnType::Integer,
+                ColumnType::String,
+                ColumnType::String,
+                ColumnType::String
+            ]
+        );
+
+        let table = Table::from_csv("Id\n1\n2", &CsvOptions::new()).unwrap();
+        assert_eq!(table.columns[0].get_type(), ColumnType::String);
+        assert_eq!(table.get_entries().len(), 2);
+    }
+
+    #[test]
+    fn final_empty_line() {
+        let table = Table::from_csv("A,B\n1,2\n\n", &CsvOptions::new()).unwrap();
+        assert_eq!(table.get_entries().len(), 1);
+        let table = Table::from_csv("A\r\n1\r\n\r\n", &CsvOptions::new()).unwrap();
+        assert_eq!(table.get_entries().len(), 1);
+        let table = Table::from_csv("A\r\n\"\"\r\n", &CsvOptions::new()).unwrap();
+        assert_eq!(table.get_entries()[0].get_values(), vec!["".into()]);
+    }
+
+    #[test]
+    fn single_column_with_nulls() {
+        let mut table = Table::new(vec![Column::new("Nick", ColumnType::String)]).unwrap();
+        table.insert(vec!["a".into()]).unwrap();
+        table.insert(vec![Value::Null(ColumnType::String)]).unwrap();
+        table.insert(vec!["".into()]).unwrap();
+        let options = CsvOptions::new();
+        let csv = table.to_csv(&options).unwrap();
+        assert_eq!(csv, "Nick\r\na\r\n\r\n\"\"\r\n");
+        assert_eq!(Table::from_csv(&csv, &options), Ok(table.clone()));
+
+        table.insert(vec![Value::Null(ColumnType::String)]).unwrap();
+        assert!(table.to_csv(&options).is_err());
+    }
+
+    #[test]
+    fn invalid_csv() {
+        let options = CsvOptions::new();
+        assert!(Table::from_csv("", &options).is_err());
+        assert!(Table::from_csv("A,B\n1\n", &options).is_err());
+        assert!(Table::from_csv("A\n\"open\n", &options).is_err());
+        assert!(Table::from_csv("A\n\"a\"b\n", &options).is_err());
+        assert!(Table::from_csv("A\na\"b\n", &options).is_err());
+        assert!(Table::from_csv("A\nx\n", &options.clone().keys(&["B"])).is_err());
+        assert!(Table::from_csv(
+            "A\nx\n",
+            &options.clone().column_type("A", ColumnType::Integer)
+        )
+        .is_err());
+        assert!(Table::from_csv("A\nx\nx\n", &options.clone().keys(&["A"])).is_err());
+
+        for delimiter in ['"', '\r', '\n'] {
+            let options = options.clone().delimiter(delimiter);
+            assert!(Table::from_csv("A\nx\n", &options).is_err());
+            assert!(create_test_table().to_csv(&options).is_err());
+        }
+    }
+}
@@ -0,0 +1,876 @@
+use crate::column::Column;
+use crate::entry::Entry;
+use crate::file;
+use crate::foreign_key::{self, Action, ForeignKey};
+use crate::query::{self, QueryError, QueryResult, Statement};
+use crate::serializer::Serializable;
+use crate::table::Table;
+use crate::values::Value;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CATALOG_FILE: &str = "catalog";
+const FOREIGN_KEY_PREFIX: &str = "foreign key,";
+
+/// A collection of named tables, stored together in a directory.
+///
+/// The directory contains a `catalog` file, listing the name, file and columns
+/// of every table and the foreign keys between them, and one file per table in
+/// the format of `Table::serialize`.
+#[derive(Clone, Debug)]
+pub struct Database {
+    path: PathBuf,
+    tables: HashMap<String, Table>,
+    foreign_keys: Vec<ForeignKey>,
+    // The table files written by the last save or found on open, so that the
+    // files of dropped tables can be removed.
+    files: HashSet<String>,
+}
+
+impl Database {
+    /// Creates a new empty database, which will be stored in the given directory.
+    /// Nothing is written until `save` is called.
+    pub fn new(path: &Path) -> Database {
+        Database {
+            path: path.to_path_buf(),
+            tables: HashMap::new(),
+            foreign_keys: vec![],
+            files: HashSet::new(),
+        }
+    }
+
+    /// Loads the database stored in the given directory.
+    /// Will error if a table file does not match its schema in the catalog, if
+    /// the catalog lists another file than the one `save` writes for a table,
+    /// or if the tables violate a foreign key.
+    pub fn open(path: &Path) -> Result<Database, String> {
+        let catalog = fs::read_to_string(path.join(CATALOG_FILE)).map_err(|e| e.to_string())?;
+
+        let mut database = Database::new(path);
+        let mut foreign_keys = vec![];
+        for line in catalog.lines().filter(|l| !l.is_empty()) {
+            if let Some(rest) = line.strip_prefix(FOREIGN_KEY_PREFIX) {
+                foreign_keys.push(deserialize_foreign_key(rest)?);
+                continue;
+            }
+
+            let (name, file, columns) = deserialize_catalog_line(line)?;
+            // Otherwise the catalog could point outside of the directory, both
+            // for reading and for removing stale files on save.
+            if file != file_name(&name) {
+                return Err(format!("Invalid file of table {}: {}", name, file));
+            }
+
+            let table = Table::from_file(&path.join(&file))?;
+            if table.columns != columns {
+                return Err(format!(
+                    "Schema of table {} does not match the catalog",
+                    name
+                ));
+            }
+
+            if database.tables.insert(name.clone(), table).is_some() {
+                return Err(format!("Table {} is listed twice in the catalog", name));
+            }
+            database.files.insert(file);
+        }
+
+        for foreign_key in foreign_keys {
+            database.add_foreign_key(foreign_key)?;
+        }
+
+        Ok(database)
+    }
+
+    /// Writes all tables and the catalog into the directory of the database.
+    /// The directory is created if it does not exist yet, files of dropped
+    /// tables are removed.
+    /// Will error without writing anything if the tables violate a foreign key.
+    pub fn save(&mut self) -> Result<(), String> {
+        self.check_foreign_keys()?;
+        fs::create_dir_all(&self.path).map_err(|e| e.to_string())?;
+
+        let names = self.table_names();
+        let mut files = HashSet::new();
+        for name in &names {
+            if !files.insert(file_name(name)) {
+                return Err(format!(
+                    "Another table is stored in the file of table {}",
+                    name
+                ));
+            }
+        }
+
+        let mut catalog = String::new();
+        for name in names {
+            let table = &self.tables[&name];
+            let file = file_name(&name);
+
+            table.write_file(&self.path.join(&file))?;
+
+            catalog.push_str(&serialize_catalog_line(&name, &file, &table.columns));
+            catalog.push('\n');
+        }
+        for foreign_key in &self.foreign_keys {
+            catalog.push_str(&serialize_foreign_key(foreign_key));
+            catalog.push('\n');
+        }
+
+        file::write_atomic(&self.path.join(CATALOG_FILE), catalog.as_bytes())?;
+
+        for stale in self.files.difference(&files) {
+            fs::remove_file(self.path.join(stale)).map_err(|e| e.to_string())?;
+        }
+        self.files = files;
+
+        Ok(())
+    }
+
+    /// Creates a new empty table. Will error if a table with the same name
+    /// already exists or if the columns are invalid for a table.
+    pub fn create_table<T: AsRef<str>>(
+        &mut self,
+        name: T,
+        columns: Vec<Column>,
+    ) -> Result<&mut Table, String> {
+        let name = name.as_ref();
+        if self.tables.contains_key(name) {
+            return Err(format!("Table already exists: {}", name));
+        }
+
+        let table = Table::new(columns)?;
+        Ok(self.tables.entry(name.to_string()).or_insert(table))
+    }
+
+    /// Removes the table and its foreign keys from the database and returns it.
+    /// Will error if the table is referenced by a foreign key of another table.
+    pub fn drop_table(&mut self, name: &str) -> Result<Table, String> {
+        if let Some(foreign_key) = self
+            .foreign_keys
+            .iter()
+            .find(|f| f.referenced_table == name && f.table != name)
+        {
+            return Err(format!(
+                "Table {} is referenced by foreign key {}",
+                name, foreign_key
+            ));
+        }
+
+        let table = self
+            .tables
+            .remove(name)
+            .ok_or_else(|| format!("Unknown table: {}", name))?;
+        self.foreign_keys.retain(|f| f.table != name);
+        Ok(table)
+    }
+
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.get(name)
+    }
+
+    /// Direct access to a table. Changes made this way are not checked
+    /// against the foreign keys until the database is saved.
+    pub fn table_mut(&mut self, name: &str) -> Option<&mut Table> {
+        self.tables.get_mut(name)
+    }
+
+    /// Adds a foreign key between two tables of the database.
+    /// Will error if the foreign key does not fit the tables or if the existing
+    /// entries violate it.
+    pub fn add_foreign_key(&mut self, foreign_key: ForeignKey) -> Result<(), String> {
+        if self.foreign_keys.contains(&foreign_key) {
+            return Err(format!("Foreign key already exists: {}", foreign_key));
+        }
+
+        foreign_key.validate(&self.tables)?;
+        foreign_key.check(&self.tables)?;
+        self.foreign_keys.push(foreign_key);
+
+        Ok(())
+    }
+
+    pub fn foreign_keys(&self) -> &[ForeignKey] {
+        &self.foreign_keys
+    }
+
+    /// Inserts into a table, see `Table::insert`.
+    /// Will error if the entry references a missing entry.
+    pub fn insert(&mut self, table: &str, entry: Vec<Value>) -> Result<(), String> {
+        get_table_mut(&mut self.tables, table)?.insert(entry)?;
+
+        let target = &self.tables[table];
+        let inserted = &target.entries[target.entries.len() - 1..];
+        if let Err(e) =
+            foreign_key::check_entries(&self.tables, &self.foreign_keys, table, inserted)
+        {
+            get_table_mut(&mut self.tables, table)?.entries.pop();
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Updates an entry of a table, see `Table::update`.
+    /// If the key changes, the update actions of the referencing foreign keys are done.
+    pub fn update(
+        &mut self,
+        table: &str,
+        keys: Vec<Value>,
+        entry: Vec<Value>,
+    ) -> Result<bool, String> {
+        self.modify(table, |tables, foreign_keys, _| {
+            let target = get_table_mut(tables, table)?;
+            let index = match target.position(&keys) {
+                Some(index) => index,
+                None => return Ok(false),
+            };
+            target.replace(index, entry)?;
+            let new_keys = target.entries[index].get_key_values();
+
+            let updated = &tables[table].entries[index..=index];
+            foreign_key::check_entries(tables, foreign_keys, table, updated)?;
+            foreign_key::on_keys_changed(tables, foreign_keys, table, vec![(keys, new_keys)])?;
+            Ok(true)
+        })
+    }
+
+    /// Removes an entry of a table, see `Table::remove`.
+    /// The delete actions of the referencing foreign keys are done.
+    pub fn remove(&mut self, table: &str, keys: Vec<Value>) -> Result<bool, String> {
+        self.modify(table, |tables, foreign_keys, _| {
+            let target = get_table_mut(tables, table)?;
+            let removed = target
+                .position(&keys)
+                .map(|index| target.entries[index].clone())
+                .into_iter()
+                .collect();
+            if !target.remove(keys) {
+                return Ok(false);
+            }
+
+            foreign_key::on_entries_removed(tables, foreign_keys, table, removed)?;
+            Ok(true)
+        })
+    }
+
+    /// The names of all tables, sorted.
+    pub fn table_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.tables.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Executes a SQL statement on the tables of the database, see `query`.
+    /// The actions of the foreign keys are done for updated and deleted entries,
+    /// statements violating a foreign key leave the tables unchanged.
+    /// Changes are only written to disk on `save`.
+    pub fn execute(&mut self, query: &str) -> Result<QueryResult, QueryError> {
+        let statement = query::parse(query)?;
+        if let Statement::Select(_) = statement {
+            return Ok(query::execute_statement(&mut self.tables, &statement)?);
+        }
+
+        let table = match &statement {
+            Statement::Insert(insert) => &insert.table,
+            Statement::Update(update) => &update.table,
+            Statement::Delete(delete) => &delete.table,
+            Statement::CreateTable(create) => &create.name,
+            Statement::Select(_) => unreachable!(),
+        };
+        Ok(self.modify(table, |tables, foreign_keys, before| {
+            let result = query::execute_statement(tables, &statement)?;
+
+            // Inserts append entries, updates and deletes keep the order of the
+            // remaining entries.
+            match (&statement, before) {
+                (Statement::Insert(insert), Some(before)) => {
+                    let inserted = &tables[&insert.table].entries[before.entries.len()..];
+                    foreign_key::check_entries(tables, foreign_keys, &insert.table, inserted)?;
+                }
+                (Statement::Update(update), Some(before)) => {
+                    let after = &tables[&update.table].entries;
+                    let updated: Vec<Entry> = before
+                        .entries
+                        .iter()
+                        .zip(after)
+                        .filter(|(old, new)| old != new)
+                        .map(|(_, new)| new.clone())
+                        .collect();
+                    foreign_key::check_entries(tables, foreign_keys, &update.table, &updated)?;
+
+                    let changes = before
+                        .entries
+                        .iter()
+                        .zip(after)
+                        .map(|(old, new)| (old.get_key_values(), new.get_key_values()))
+                        .collect();
+                    foreign_key::on_keys_changed(tables, foreign_keys, &update.table, changes)?;
+                }
+                (Statement::Delete(delete), Some(before)) => {
+                    let mut remaining = tables[&delete.table].entries.iter().peekable();
+                    let mut removed = vec![];
+                    for entry in &before.entries {
+                        if remaining.peek() == Some(&entry) {
+                            remaining.next();
+                        } else {
+                            removed.push(entry.clone());
+                        }
+                    }
+                    foreign_key::on_entries_removed(tables, foreign_keys, &delete.table, removed)?;
+                }
+                _ => {}
+            }
+
+            Ok(result)
+        })?)
+    }
+
+    // Applies the modification to the tables in place and passes it the
+    // table as it was before. Only the tables the modification can change,
+    // the table and the tables its changes cascade to through foreign keys,
+    // are copied and restored if it fails.
+    // The modification has to check the foreign keys for the entries it changes.
+    fn modify<T, F>(&mut self, table: &str, modification: F) -> Result<T, String>
+    where
+        F: FnOnce(&mut HashMap<String, Table>, &[ForeignKey], Option<&Table>) -> Result<T, String>,
+    {
+        let mut affected = vec![table.to_string()];
+        let mut index = 0;
+        while index < affected.len() {
+            for foreign_key in &self.foreign_keys {
+                let changes = foreign_key.on_delete != Action::Restrict
+                    || foreign_key.on_update != Action::Restrict;
+                if changes
+                    && foreign_key.referenced_table == affected[index]
+                    && !affected.contains(&foreign_key.table)
+                {
+                    affected.push(foreign_key.table.clone());
+                }
+            }
+            index += 1;
+        }
+        let snapshot: Vec<(String, Option<Table>)> = affected
+            .into_iter()
+            .map(|name| {
+                let table = self.tables.get(&name).cloned();
+                (name, table)
+            })
+            .collect();
+
+        let result = modification(&mut self.tables, &self.foreign_keys, snapshot[0].1.as_ref());
+        if result.is_err() {
+            for (name, table) in snapshot {
+                match table {
+                    Some(table) => self.tables.insert(name, table),
+                    None => self.tables.remove(&name),
+                };
+            }
+        }
+
+        result
+    }
+
+    fn check_foreign_keys(&self) -> Result<(), String> {
+        for foreign_key in &self.foreign_keys {
+            foreign_key.check(&self.tables)?;
+        }
+        Ok(())
+    }
+}
+
+fn get_table_mut<'a>(
+    tables: &'a mut HashMap<String, Table>,
+    name: &str,
+) -> Result<&'a mut Table, String> {
+    tables
+        .get_mut(name)
+        .ok_or_else(|| format!("Unknown table: {}", name))
+}
+
+// The file of a table. Names that are safe on every file system keep their
+// name after a "t-", all others are hex encoded after a "x-", so that no two
+// names share a file, even on case insensitive file systems.
+fn file_name(table_name: &str) -> String {
+    if !table_name.is_empty()
+        && table_name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    {
+        format!("t-{}.table", table_name)
+    } else {
+        let hex: String = table_name.bytes().map(|b| format!("{:02x}", b)).collect();
+        format!("x-{}.table", hex)
+    }
+}
+
+fn serialize_catalog_line(name: &str, file: &str, columns: &[Column]) -> String {
+    format!(
+        "{},{},{}",
+        quote(name),
+        quote(file),
+        columns.to_vec().serialize()
+    )
+}
+
+fn deserialize_catalog_line(line: &str) -> Result<(String, String, Vec<Column>), String> {
+    let (name, rest) = deserialize_quoted(line)?;
+    let (file, rest) = deserialize_quoted(&rest)?;
+    let (columns, _) = Column::deserialize_columns(rest)?;
+
+    Ok((name, file, columns))
+}
+
+fn serialize_foreign_key(foreign_key: &ForeignKey) -> String {
+    let mut line = format!(
+        "{}{},{},{},{}",
+        FOREIGN_KEY_PREFIX,
+        quote(&foreign_key.table),
+        quote(&foreign_key.referenced_table),
+        foreign_key.on_delete,
+        foreign_key.on_update
+    );
+    for column in &foreign_key.columns {
+        line.push(',');
+        line.push_str(&quote(column));
+    }
+    line
+}
+
+fn deserialize_foreign_key(line: &str) -> Result<ForeignKey, String> {
+    let invalid = || format!("Invalid catalog line: {}{}", FOREIGN_KEY_PREFIX, line);
+
+    let (table, rest) = deserialize_quoted(line)?;
+    let (referenced_table, rest) = deserialize_quoted(&rest)?;
+    let mut parts = rest.splitn(3, ',');
+    let on_delete: Action = parts.next().ok_or_else(invalid)?.parse()?;
+    let on_update: Action = parts.next().ok_or_else(invalid)?.parse()?;
+
+    // Every column is followed by a "," for `deserialize_quoted`.
+    let mut rest = format!("{},", parts.next().ok_or_else(invalid)?);
+    let mut columns = vec![];
+    while !rest.is_empty() {
+        let (column, remaining) = deserialize_quoted(&rest)?;
+        columns.push(column);
+        rest = remaining;
+    }
+
+    let columns: Vec<&str> = columns.iter().map(|c| c.as_str()).collect();
+    Ok(ForeignKey::new(table, &columns, referenced_table)
+        .on_delete(on_delete)
+        .on_update(on_update))
+}
+
+// Surrounds the string with " and escapes every "\", """ and "," in it with a "\".
+fn quote(str: &str) -> String {
+    let mut result = String::from("\"");
+    for c in str.chars() {
+        if c == '\\' || c == '"' || c == ',' {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result.push('"');
+
+    result
+}
+
+// Reads a string written by `quote` followed by a ",".
+fn deserialize_quoted(str: &str) -> Result<(String, String), String> {
+    let invalid = || format!("Invalid catalog line: {}", str);
+
+    let mut chars = str.strip_prefix('"').ok_or_else(invalid)?.chars();
+    let mut value = String::new();
+    loop {
+        match chars.next().ok_or_else(invalid)? {
+            '\\' => value.push(chars.next().ok_or_else(invalid)?),
+            '"' => break,
+            c => value.push(c),
+        }
+    }
+    let rest = chars.as_str().strip_prefix(',').ok_or_else(invalid)?;
+
+    Ok((value, rest.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Database;
+    use crate::column::Column;
+    use crate::foreign_key::{Action, ForeignKey};
+    use crate::query::QueryResult;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn test_directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "file-minidb-database-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    fn create_test_database(path: &Path) -> Database {
+        let mut database = Database::new(path);
+
+        let people = database
+            .create_table(
+                "people",
+                vec![
+                    Column::key("First, Name", ColumnType::String),
+                    Column::new("Age", ColumnType::Integer),
+                ],
+            )
+            .unwrap();
+        people.insert(vec!["Peter".into(), 15.into()]).unwrap();
+        people.insert(vec!["Wendy".into(), 12.into()]).unwrap();
+
+        database
+            .create_table("pet owners", vec![Column::new("Name", ColumnType::String)])
+            .unwrap()
+            .insert(vec!["Peter".into()])
+            .unwrap();
+
+        database
+    }
+
+    // The pet owners reference the people by name.
+    fn create_foreign_key_database(path: &Path, action: Action) -> Database {
+        let mut database = create_test_database(path);
+        database
+            .add_foreign_key(
+                ForeignKey::new("pet owners", &["Name"], "people")
+                    .on_delete(action)
+                    .on_update(action),
+            )
+            .unwrap();
+        database
+    }
+
+    #[test]
+    fn save_and_open() {
+        let path = test_directory("save_and_open");
+        let mut database = create_test_database(&path);
+
+        database.save().unwrap();
+        let opened = Database::open(&path).unwrap();
+
+        assert_eq!(opened.table_names(), vec!["people", "pet owners"]);
+        assert_eq!(opened.table("people"), database.table("people"));
+        assert_eq!(opened.table("pet owners"), database.table("pet owners"));
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn similar_names() {
+        let path = test_directory("similar_names");
+        let mut database = Database::new(&path);
+        // Would share a file if names were only hex encoded when needed
+        for name in ["people!", "x70656f706c6521", "People", "people"] {
+            database
+                .create_table(name, vec![Column::new("Name", ColumnType::String)])
+                .unwrap()
+                .insert(vec![name.into()])
+                .unwrap();
+        }
+        database.save().unwrap();
+
+        let opened = Database::open(&path).unwrap();
+        for name in database.table_names() {
+            assert_eq!(opened.table(&name), database.table(&name));
+        }
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 5);
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn catalog_with_other_file() {
+        let path = test_directory("catalog_with_other_file");
+        create_test_database(&path).save().unwrap();
+        let catalog = fs::read_to_string(path.join("catalog")).unwrap();
+        assert!(catalog.contains("\"t-people.table\""));
+
+        for file in ["../t-people.table", "/tmp/t-people.table", "people.table"] {
+            fs::write(
+                path.join("catalog"),
+                catalog.replace("\"t-people.table\"", &format!("\"{}\"", file)),
+            )
+            .unwrap();
+            assert!(Database::open(&path).is_err());
+        }
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn quoted_names() {
+        let path = test_directory("quoted_names");
+        let mut database = Database::new(&path);
+        for name in ["a\"b", "a\\", "\\\",\""] {
+            database
+                .create_table(name, vec![Column::key("Name", ColumnType::String)])
+                .unwrap()
+                .insert(vec![name.into()])
+                .unwrap();
+        }
+        database
+            .table_mut("a\\")
+            .unwrap()
+            .insert(vec!["a\"b".into()])
+            .unwrap();
+        database
+            .add_foreign_key(ForeignKey::new("a\"b", &["Name"], "a\\"))
+            .unwrap();
+        database.save().unwrap();
+
+        let opened = Database::open(&path).unwrap();
+        assert_eq!(opened.table_names(), database.table_names());
+        for name in database.table_names() {
+            assert_eq!(opened.table(&name), database.table(&name));
+        }
+        assert_eq!(opened.foreign_keys(), database.foreign_keys());
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn drop_table_removes_file() {
+        let path = test_directory("drop_table");
+        let mut database = create_test_database(&path);
+        database.save().unwrap();
+
+        let mut opened = Database::open(&path).unwrap();
+        assert!(opened.drop_table("people").is_ok());
+        assert!(opened.drop_table("people").is_err());
+        opened.save().unwrap();
+
+        assert!(!path.join("t-people.table").exists());
+        assert_eq!(
+            Database::open(&path).unwrap().table_names(),
+            vec!["pet owners"]
+        );
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn create_existing_table() {
+        let path = test_directory("create_existing");
+        let mut database = create_test_database(&path);
+
+        assert!(database
+            .create_table("people", vec![Column::new("Name", ColumnType::String)])
+            .is_err());
+        assert_eq!(database.table("people").unwrap().get_entries().len(), 2);
+    }
+
+    #[test]
+    fn execute_and_modify() {
+        let path = test_directory("execute");
+        let mut database = create_test_database(&path);
+
+        database
+            .table_mut("people")
+            .unwrap()
+            .insert(vec!["John".into(), 10.into()])
+            .unwrap();
+        assert_eq!(
+            database.execute("DELETE FROM people WHERE Age < 14"),
+            Ok(QueryResult::Affected(2))
+        );
+        database.save().unwrap();
+
+        assert_eq!(
+            Database::open(&path)
+                .unwrap()
+                .table("people")
+                .unwrap()
+                .get_entries()
+                .len(),
+            1
+        );
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn modify_without_keys() {
+        let path = test_directory("modify_without_keys");
+        let mut database = create_test_database(&path);
+
+        assert_eq!(database.remove("pet owners", vec![]), Ok(false));
+        assert_eq!(
+            database.update("pet owners", vec![], vec!["Wendy".into()]),
+            Ok(false)
+        );
+        assert_eq!(
+            database.table("pet owners").unwrap().get_entries()[0].get_values(),
+            vec![Value::String("Peter".to_string())]
+        );
+    }
+
+    #[test]
+    fn schema_mismatch() {
+        let path = test_directory("schema_mismatch");
+        let mut database = create_test_database(&path);
+        database.save().unwrap();
+
+        // Replace the table file behind the back of the catalog
+        let mut other = create_test_database(&path);
+        other.drop_table("people").unwrap();
+        other
+            .create_table("people", vec![Column::key("Name", ColumnType::String)])
+            .unwrap();
+        other
+            .table("people")
+            .unwrap()
+            .write_file(&path.join("t-people.table"))
+            .unwrap();
+
+        assert_eq!(
+            Database::open(&path).err(),
+            Some("Schema of table people does not match the catalog".to_string())
+        );
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn foreign_key_insert() {
+        let path = test_directory("foreign_key_insert");
+        let mut database = create_foreign_key_database(&path, Action::Restrict);
+
+        assert!(database.insert("pet owners", vec!["Wendy".into()]).is_ok());
+        assert!(database
+            .insert("pet owners", vec![Value::Null(ColumnType::String)])
+            .is_ok());
+        assert!(database.insert("pet owners", vec!["John".into()]).is_err());
+        assert!(database
+            .execute(r#"INSERT INTO "pet owners" VALUES ('John')"#)
+            .is_err());
+        assert_eq!(database.table("pet owners").unwrap().get_entries().len(), 3);
+
+        assert!(database
+            .add_foreign_key(ForeignKey::new("pet owners", &["Name"], "people"))
+            .is_err());
+    }
+
+    #[test]
+    fn foreign_key_restrict() {
+        let path = test_directory("foreign_key_restrict");
+        let mut database = create_foreign_key_database(&path, Action::Restrict);
+
+        assert!(database.remove("people", vec!["Peter".into()]).is_err());
+        assert!(database
+            .update(
+                "people",
+                vec!["Peter".into()],
+                vec!["Pan".into(), 15.into()]
+            )
+            .is_err());
+        assert!(database
+            .execute("DELETE FROM people WHERE Age > 14")
+            .is_err());
+        assert_eq!(database.remove("people", vec!["Wendy".into()]), Ok(true));
+        assert_eq!(database.table("people").unwrap().get_entries().len(), 1);
+
+        assert!(database.drop_table("people").is_err());
+        assert!(database.drop_table("pet owners").is_ok());
+        assert!(database.foreign_keys().is_empty());
+    }
+
+    #[test]
+    fn foreign_key_cascade() {
+        let path = test_directory("foreign_key_cascade");
+        let mut database = create_foreign_key_database(&path, Action::Cascade);
+
+        assert_eq!(
+            database.execute(r#"UPDATE people SET "First, Name" = 'Pan' WHERE Age = 15"#),
+            Ok(QueryResult::Affected(1))
+        );
+        assert_eq!(
+            database.table("pet owners").unwrap().get_entries()[0].get_values(),
+            vec![Value::String("Pan".to_string())]
+        );
+
+        assert_eq!(
+            database.execute("DELETE FROM people WHERE Age = 15"),
+            Ok(QueryResult::Affected(1))
+        );
+        assert!(database
+            .table("pet owners")
+            .unwrap()
+            .get_entries()
+            .is_empty());
+    }
+
+    #[test]
+    fn foreign_key_rollback() {
+        let path = test_directory("foreign_key_rollback");
+        let mut database = create_foreign_key_database(&path, Action::Cascade);
+        database
+            .create_table("doctors", vec![Column::new("Patient", ColumnType::String)])
+            .unwrap()
+            .insert(vec!["Peter".into()])
+            .unwrap();
+        database
+            .add_foreign_key(ForeignKey::new("doctors", &["Patient"], "people"))
+            .unwrap();
+        let before = database.clone();
+
+        // Cascades to the pet owners before the doctors restrict it
+        assert!(database.remove("people", vec!["Peter".into()]).is_err());
+        assert!(database
+            .execute(r#"UPDATE "pet owners" SET Name = 'John'"#)
+            .is_err());
+        for name in before.table_names() {
+            assert_eq!(database.table(&name), before.table(&name));
+        }
+    }
+
+    #[test]
+    fn foreign_key_set_null() {
+        let path = test_directory("foreign_key_set_null");
+        let mut database = create_foreign_key_database(&path, Action::SetNull);
+
+        assert_eq!(database.remove("people", vec!["Peter".into()]), Ok(true));
+        assert_eq!(
+            database.table("pet owners").unwrap().get_entries()[0].get_values(),
+            vec![Value::Null(ColumnType::String)]
+        );
+    }
+
+    #[test]
+    fn foreign_key_save_and_open() {
+        let path = test_directory("foreign_key_save_and_open");
+        let mut database = create_foreign_key_database(&path, Action::Cascade);
+        database.save().unwrap();
+
+        let mut opened = Database::open(&path).unwrap();
+        assert_eq!(opened.foreign_keys(), database.foreign_keys());
+
+        // Changes bypassing the foreign keys are found on save and on open
+        opened
+            .table_mut("people")
+            .unwrap()
+            .remove(vec!["Peter".into()]);
+        assert!(opened.save().is_err());
+        opened
+            .table("people")
+            .unwrap()
+            .write_file(&path.join("t-people.table"))
+            .unwrap();
+        assert!(Database::open(&path).is_err());
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 use crate::column::Column;
 use crate::entry::Entry;
-use crate::serializer::Serializable;
+use crate::hash::crc32;
+use crate::serializer::{Serializable, CURRENT_VERSION, HEADER_PREFIX};
 use crate::table::Table;
 use crate::types::ColumnType;
 use crate::values::Value;
@@ -33,10 +34,114 @@ fn starts_with_and_remove(str: &str, pattern: &str) -> (bool, String) {
     }
 }
 
+// The metadata in the header line of the text format, see `Table::serialize_version`.
+#[derive(Debug)]
+pub(crate) struct Header {
+    pub(crate) version: u32,
+    pub(crate) entries: Option<usize>,
+}
+
+impl Header {
+    // The header of a string without header line.
+    pub(crate) const NONE: Header = Header {
+        version: 0,
+        entries: None,
+    };
+
+    // Splits off the header line, a string without header is version 0.
+    fn deserialize(str: &str) -> Result<(Header, &str), String> {
+        if !str.starts_with(HEADER_PREFIX) {
+            return Ok((Header::NONE, str));
+        }
+
+        let (line, rest) = str.split_once('\n').unwrap_or((str, ""));
+        Ok((Header::deserialize_line(line)?, rest))
+    }
+
+    pub(crate) fn deserialize_line(line: &str) -> Result<Header, String> {
+        let invalid = || format!("Invalid header: {}", line);
+
+        let mut version = None;
+        let mut entries = None;
+        for field in line[HEADER_PREFIX.len()..].split_whitespace() {
+            let (name, value) = field.split_once('=').ok_or_else(invalid)?;
+            match name {
+                "version" => version = Some(value.parse::<u32>().map_err(|_| invalid())?),
+                "entries" => entries = Some(value.parse::<usize>().map_err(|_| invalid())?),
+                // Metadata of later versions
+                _ => {}
+            }
+        }
+
+        let version = version.ok_or_else(invalid)?;
+        if version == 0 || version > CURRENT_VERSION {
+            return Err(format!("Unsupported format version: {}", version));
+        }
+
+        Ok(Header { version, entries })
+    }
+
+    // Checks the checksum in front of a row and returns the row without it.
+    pub(crate) fn verify_checksum<'a>(&self, line: &'a str) -> Result<&'a str, String> {
+        if self.version < 2 {
+            return Ok(line);
+        }
+
+        let (checksum, data) = line
+            .split_once(' ')
+            .ok_or_else(|| "Missing checksum".to_string())?;
+        let checksum =
+            u32::from_str_radix(checksum, 16).map_err(|_| "Invalid checksum".to_string())?;
+        if crc32(data.as_bytes()) != checksum {
+            return Err("Checksum mismatch".to_string());
+        }
+
+        Ok(data)
+    }
+}
+
+/// The version of the text format the string is in, see `Table::serialize_version`.
+pub fn format_version(str: &str) -> Result<u32, String> {
+    Header::deserialize(str).map(|(header, _)| header.version)
+}
+
+/// A row of a table that could not be read or failed its checksum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorruptRow {
+    /// The line of the row, starting at 1.
+    pub line: usize,
+    pub content: String,
+    pub error: String,
+}
+
 impl Table {
-    /// Deserialize a table from the given string.
-    /// If the string does not represent a table, a error will be returned.
+    /// Deserialize a table from the given string, in any version of the text format.
+    /// If the string does not represent a table or a row is corrupt, a error will be returned.
     pub fn deserialize(str: String) -> Result<Self, String> {
+        let (table, corrupt) = Table::deserialize_lenient(str)?;
+
+        match corrupt.first() {
+            None => Ok(table),
+            Some(row) => Err(format!(
+                "Corrupt rows in lines {}, first in line {}: {}",
+                corrupt
+                    .iter()
+                    .map(|r| r.line.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                row.line,
+                row.error
+            )),
+        }
+    }
+
+    /// Deserialize a table from the given string, like `deserialize`, but
+    /// returns the rows that could not be read or failed their checksum
+    /// separately instead of failing.
+    pub fn deserialize_lenient(str: String) -> Result<(Self, Vec<CorruptRow>), String> {
+        let (header, str) = Header::deserialize(&str)?;
+        // Lines are counted in the whole string
+        let first_line_number = if header.version == 0 { 1 } else { 2 };
         let mut lines = str.split('\n');
 
         let first_line = lines.next();
@@ -44,39 +149,54 @@ impl Table {
             return Err("String is empty".to_string());
         }
 
-        let columns = Column::deserialize_columns(first_line.unwrap().to_string());
+        let (columns, _) = Column::deserialize_columns(first_line.unwrap().to_string())?;
 
-        let mut table = Table::new(columns.clone().unwrap().0).unwrap();
+        let mut table = Table::new(columns.clone())?;
 
         let mut next_line = lines.next();
+        let mut entries = vec![];
+        let mut corrupt = vec![];
 
-        while next_line.is_some() {
-            if !next_line.unwrap().is_empty() {
-                let entry_opt = Entry::deserialize_data(
-                    next_line.unwrap().to_string(),
-                    columns.clone().unwrap().0,
-                );
-
-                if let Ok(entry) = entry_opt {
-                    table
-                        .insert(entry.values.iter().map(|(_, v)| v.clone()).collect())
-                        .unwrap();
-                } else {
-                    return Err(entry_opt.err().unwrap());
-                }
-
-                next_line = lines.next()
-            } else {
+        for line in first_line_number + 1.. {
+            if next_line.is_none() || next_line.unwrap().is_empty() {
                 break;
             }
+            let content = next_line.unwrap();
+
+            let entry_opt = header
+                .verify_checksum(content)
+                .and_then(|data| Entry::deserialize_data(data.to_string(), columns.clone()));
+
+            match entry_opt {
+                Ok(entry) => entries.push(entry.values.into_iter().map(|(_, v)| v).collect()),
+                Err(error) => corrupt.push(CorruptRow {
+                    line,
+                    content: content.to_string(),
+                    error,
+                }),
+            }
+
+            next_line = lines.next()
         }
 
-        Ok(table)
+        table.insert_all(entries)?;
+
+        if let Some(expected) = header.entries {
+            if expected != table.entries.len() + corrupt.len() {
+                return Err(format!(
+                    "Expected {} entries, found {}",
+                    expected,
+                    table.entries.len() + corrupt.len()
+                ));
+            }
+        }
+
+        Ok((table, corrupt))
     }
 }
 
 impl Entry {
-    fn deserialize_data(str: String, columns: Vec<Column>) -> Result<Self, String> {
+    pub(crate) fn deserialize_data(str: String, columns: Vec<Column>) -> Result<Self, String> {
         let mut result: Vec<(Column, Value)> = vec![];
         let mut working_str = str;
         for column in columns {
@@ -86,13 +206,12 @@ impl Entry {
             if let Some(split_ok) = split {
                 let (first, rest) = split_ok;
 
-                let mut first_mut = first.clone();
-
-                first_mut.remove(0);
-                first_mut.remove(first_mut.len() - 1);
-                first_mut = first_mut.replace("\\,", ",");
-
-                let value_res = Entry::deserialize_value(first_mut, column.get_type());
+                let value_res = if first == "null" {
+                    Ok(Value::Null(column.get_type()))
+                } else {
+                    unquote(&first)
+                        .and_then(|value| Entry::deserialize_value(value, column.get_type()))
+                };
 
                 if let Ok(value) = value_res {
                     result.push((column, value));
@@ -103,12 +222,14 @@ impl Entry {
                 working_str = rest;
                 working_str.remove(0);
             } else {
-                if !working_str.is_empty() {
-                    working_str.remove(0);
-                    working_str.remove(working_str.len() - 1);
-                    working_str = working_str.replace("\\,", ",");
-                }
-                let value_res = Entry::deserialize_value(working_str, column.get_type());
+                let value_res = if working_str == "null" {
+                    Ok(Value::Null(column.get_type()))
+                } else if working_str.is_empty() {
+                    Entry::deserialize_value(working_str, column.get_type())
+                } else {
+                    unquote(&working_str)
+                        .and_then(|value| Entry::deserialize_value(value, column.get_type()))
+                };
 
                 if let Ok(value) = value_res {
                     result.push((column, value));
@@ -137,11 +258,19 @@ impl Entry {
     }
 }
 
+// Removes the quotes around a value and unescapes it.
+fn unquote(str: &str) -> Result<String, String> {
+    str.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .map(|s| s.replace("\\,", ","))
+        .ok_or_else(|| format!("Missing quote around value {}", str))
+}
+
 impl Column {
-    fn deserialize_columns(str: String) -> Result<(Vec<Self>, String), String> {
+    pub(crate) fn deserialize_columns(str: String) -> Result<(Vec<Self>, String), String> {
         let mut rest = str;
         let mut result = vec![];
-        while rest != "" {
+        while !rest.is_empty() {
             if rest.starts_with(',') {
                 rest = rest.split_off(1);
             }
@@ -227,7 +356,7 @@ mod test {
         let column2 = super::Column::new("C2", crate::types::ColumnType::String);
         let columns = vec![column1, column2];
 
-        return super::Table::new(columns).unwrap();
+        super::Table::new(columns).unwrap()
     }
 
     fn create_test_table_with_comma() -> super::Table {
@@ -235,7 +364,7 @@ mod test {
         let column2 = super::Column::new("C2,", crate::types::ColumnType::String);
         let columns = vec![column1, column2];
 
-        return super::Table::new(columns).unwrap();
+        super::Table::new(columns).unwrap()
     }
 
     fn deserialization_equal(table: super::Table) -> bool {
@@ -267,6 +396,21 @@ mod test {
         assert!(deserialization_equal(table));
     }
 
+    #[test]
+    fn deserialize_null() {
+        let mut table = create_test_table();
+
+        table
+            .insert(vec![
+                10.into(),
+                crate::values::Value::Null(crate::types::ColumnType::String),
+            ])
+            .unwrap();
+        table.insert(vec![20.into(), "null".into()]).unwrap();
+
+        assert!(deserialization_equal(table));
+    }
+
     #[test]
     fn deserialize_with_comma_no_data() {
         let table = create_test_table_with_comma();
@@ -283,4 +427,87 @@ mod test {
 
         assert!(deserialization_equal(table));
     }
+
+    #[test]
+    fn deserialize_versions() {
+        let mut table = create_test_table();
+        table.insert(vec![10.into(), "Hello".into()]).unwrap();
+
+        for version in 0..=crate::serializer::CURRENT_VERSION {
+            let serialized = table.serialize_version(version).unwrap();
+
+            assert_eq!(super::format_version(&serialized), Ok(version));
+            assert_eq!(super::Table::deserialize(serialized), Ok(table.clone()));
+        }
+    }
+
+    #[test]
+    fn deserialize_invalid_header() {
+        let body = table_body();
+
+        assert!(super::Table::deserialize(format!(
+            "#file-minidb version=1 entries=1 created=today\n{}",
+            body
+        ))
+        .is_ok());
+        assert_eq!(
+            super::Table::deserialize(format!("#file-minidb version=1 entries=2\n{}", body)),
+            Err("Expected 2 entries, found 1".to_string())
+        );
+        assert_eq!(
+            super::Table::deserialize(format!("#file-minidb version=9\n{}", body)),
+            Err("Unsupported format version: 9".to_string())
+        );
+        assert!(super::Table::deserialize(format!("#file-minidb entries=1\n{}", body)).is_err());
+    }
+
+    #[test]
+    fn deserialize_corrupt_rows() {
+        let mut table = create_test_table();
+        table.insert(vec![10.into(), "Hello".into()]).unwrap();
+        table.insert(vec![20.into(), "World".into()]).unwrap();
+        table.insert(vec![30.into(), "!".into()]).unwrap();
+
+        // A flipped bit turning a value into another valid value
+        let serialized = table.serialize_version(2).unwrap();
+        let corrupted = serialized.replacen("\"20\"", "\"28\"", 1);
+
+        assert!(super::Table::deserialize(corrupted.clone()).is_err());
+
+        let (loaded, corrupt) = super::Table::deserialize_lenient(corrupted).unwrap();
+        assert_eq!(loaded.get_entries().len(), 2);
+        assert!(table.remove(vec![20.into()]));
+        assert_eq!(loaded, table);
+        assert_eq!(
+            corrupt,
+            vec![super::CorruptRow {
+                line: 4,
+                content: serialized.lines().nth(3).unwrap().replace("20", "28"),
+                error: "Checksum mismatch".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn deserialize_missing_quotes() {
+        let (loaded, corrupt) =
+            super::Table::deserialize_lenient("int \"A\"\nx\n\"2\"\n".to_string()).unwrap();
+        assert_eq!(loaded.get_entries()[0].get_values(), vec![2.into()]);
+        assert_eq!(corrupt.len(), 1);
+        assert_eq!(corrupt[0].line, 2);
+        assert_eq!(corrupt[0].content, "x");
+
+        let (loaded, corrupt) =
+            super::Table::deserialize_lenient("str \"A\"\n\"é\n".to_string()).unwrap();
+        assert!(loaded.get_entries().is_empty());
+        assert_eq!(corrupt.len(), 1);
+        assert_eq!(corrupt[0].content, "\"é");
+    }
+
+    fn table_body() -> String {
+        r#"key int "C1",str "C2"
+"10","Hello"
+"#
+        .to_string()
+    }
 }
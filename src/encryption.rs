@@ -0,0 +1,325 @@
+#[cfg(feature = "encryption")]
+use crate::file;
+#[cfg(feature = "encryption")]
+use crate::fingerprint;
+#[cfg(feature = "encryption")]
+use crate::hash::crc32;
+#[cfg(feature = "encryption")]
+use crate::table::Table;
+
+#[cfg(feature = "encryption")]
+use argon2::{Algorithm, Argon2, Params, Version};
+#[cfg(feature = "encryption")]
+use chacha20poly1305::aead::{Aead, Generate, KeyInit, Payload};
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+#[cfg(feature = "encryption")]
+use std::convert::TryInto;
+use std::fmt;
+#[cfg(feature = "encryption")]
+use std::path::Path;
+
+/// The first bytes of an encrypted table file.
+pub const MAGIC: &[u8] = b"FMDE";
+
+#[cfg(feature = "encryption")]
+const VERSION: u8 = 1;
+#[cfg(feature = "encryption")]
+const SALT_LENGTH: usize = 16;
+#[cfg(feature = "encryption")]
+const NONCE_LENGTH: usize = 24;
+#[cfg(feature = "encryption")]
+const KEY_LENGTH: usize = 32;
+#[cfg(feature = "encryption")]
+const HEADER_LENGTH: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LENGTH + NONCE_LENGTH + KEY_LENGTH + 4;
+
+// Argon2id costs for new files, the costs of a file are read from its header.
+// Tests use the minimum, as the defaults take seconds without optimizations.
+#[cfg(feature = "encryption")]
+const M_COST: u32 = if cfg!(test) {
+    Params::MIN_M_COST
+} else {
+    Params::DEFAULT_M_COST
+};
+#[cfg(feature = "encryption")]
+const T_COST: u32 = if cfg!(test) {
+    Params::MIN_T_COST
+} else {
+    Params::DEFAULT_T_COST
+};
+#[cfg(feature = "encryption")]
+const P_COST: u32 = Params::DEFAULT_P_COST;
+
+// The highest costs accepted from a header. The header is only protected by
+// its CRC32, so without a limit a rewritten header could make opening a file
+// take unbounded memory and time before the key can be checked.
+#[cfg(feature = "encryption")]
+const MAX_COSTS: [u32; 3] = [
+    Params::DEFAULT_M_COST,
+    Params::DEFAULT_T_COST,
+    Params::DEFAULT_P_COST,
+];
+
+/// If the bytes are an encrypted container, see `Table::write_encrypted_file`.
+pub(crate) fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// An error while reading an encrypted table file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncryptionError {
+    /// The passphrase does not match the one the file was written with.
+    WrongKey,
+    /// The file was damaged or tampered with.
+    Corrupt(String),
+    Error(String),
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionError::WrongKey => write!(f, "Wrong passphrase for the table file"),
+            EncryptionError::Corrupt(e) => write!(f, "Corrupt encrypted table file: {}", e),
+            EncryptionError::Error(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<String> for EncryptionError {
+    fn from(e: String) -> Self {
+        EncryptionError::Error(e)
+    }
+}
+
+/// The header of an encrypted table file.
+///
+/// It is `MAGIC`, the version, the Argon2id memory, time and parallelism costs
+/// as little endian u32, the salt, the nonce, a value to check the key with and
+/// the CRC32 of everything before. The table follows, encrypted with
+/// XChaCha20-Poly1305 with the header as associated data.
+#[cfg(feature = "encryption")]
+struct Header {
+    costs: [u32; 3],
+    salt: [u8; SALT_LENGTH],
+    nonce: [u8; NONCE_LENGTH],
+    key_check: [u8; KEY_LENGTH],
+}
+
+#[cfg(feature = "encryption")]
+impl Header {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut result = MAGIC.to_vec();
+        result.push(VERSION);
+        for cost in &self.costs {
+            result.extend_from_slice(&cost.to_le_bytes());
+        }
+        result.extend_from_slice(&self.salt);
+        result.extend_from_slice(&self.nonce);
+        result.extend_from_slice(&self.key_check);
+        let checksum = crc32(&result);
+        result.extend_from_slice(&checksum.to_le_bytes());
+        result
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Header, EncryptionError> {
+        let corrupt = |e: &str| EncryptionError::Corrupt(e.to_string());
+        if bytes.len() < HEADER_LENGTH || !is_encrypted(bytes) {
+            return Err(corrupt("Header too short"));
+        }
+        let (content, checksum) = bytes[..HEADER_LENGTH].split_at(HEADER_LENGTH - 4);
+        if crc32(content).to_le_bytes() != checksum {
+            return Err(corrupt("Header checksum mismatch"));
+        }
+        if content[MAGIC.len()] != VERSION {
+            return Err(EncryptionError::Error(format!(
+                "Unsupported encryption version: {}",
+                content[MAGIC.len()]
+            )));
+        }
+
+        let mut rest = &content[MAGIC.len() + 1..];
+        let mut take = |length: usize| {
+            let (taken, remaining) = rest.split_at(length);
+            rest = remaining;
+            taken
+        };
+        let mut costs = [0; 3];
+        for cost in &mut costs {
+            *cost = u32::from_le_bytes(take(4).try_into().unwrap());
+        }
+        if costs.iter().zip(&MAX_COSTS).any(|(cost, max)| cost > max) {
+            return Err(corrupt("Key derivation costs too high"));
+        }
+        Ok(Header {
+            costs,
+            salt: take(SALT_LENGTH).try_into().unwrap(),
+            nonce: take(NONCE_LENGTH).try_into().unwrap(),
+            key_check: take(KEY_LENGTH).try_into().unwrap(),
+        })
+    }
+
+    // Derives the key for the cipher and the value to check it with.
+    fn derive_key(&self, passphrase: &str) -> Result<([u8; KEY_LENGTH], [u8; KEY_LENGTH]), String> {
+        let [m_cost, t_cost, p_cost] = self.costs;
+        let params =
+            Params::new(m_cost, t_cost, p_cost, Some(2 * KEY_LENGTH)).map_err(|e| e.to_string())?;
+        let mut output = [0; 2 * KEY_LENGTH];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &self.salt, &mut output)
+            .map_err(|e| e.to_string())?;
+
+        let mut key = [0; KEY_LENGTH];
+        let mut key_check = [0; KEY_LENGTH];
+        key.copy_from_slice(&output[..KEY_LENGTH]);
+        key_check.copy_from_slice(&output[KEY_LENGTH..]);
+        Ok((key, key_check))
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl Table {
+    /// Write the table to a file like `write_file`, but encrypted with a key
+    /// derived from the passphrase. Read it with `from_encrypted_file`.
+    pub fn write_encrypted_file(&self, path: &Path, passphrase: &str) -> Result<(), String> {
+        let mut header = Header {
+            costs: [M_COST, T_COST, P_COST],
+            salt: Generate::generate(),
+            nonce: Generate::generate(),
+            key_check: [0; KEY_LENGTH],
+        };
+        let (key, key_check) = header.derive_key(passphrase)?;
+        header.key_check = key_check;
+
+        let mut content = header.to_bytes();
+        let encrypted = XChaCha20Poly1305::new(&key.into())
+            .encrypt(
+                &XNonce::from(header.nonce),
+                Payload {
+                    msg: self.serialize_current().as_bytes(),
+                    aad: &content,
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        content.extend_from_slice(&encrypted);
+
+        file::write_atomic(path, &content)
+    }
+
+    /// Load a table from a file written with `write_encrypted_file`.
+    pub fn from_encrypted_file(path: &Path, passphrase: &str) -> Result<Table, EncryptionError> {
+        let (content, fingerprint) = fingerprint::read_file(path).map_err(|e| e.to_string())?;
+        let header = Header::from_bytes(&content)?;
+        let (key, key_check) = header.derive_key(passphrase)?;
+        if key_check != header.key_check {
+            return Err(EncryptionError::WrongKey);
+        }
+
+        let (aad, encrypted) = content.split_at(HEADER_LENGTH);
+        let decrypted = XChaCha20Poly1305::new(&key.into())
+            .decrypt(
+                &XNonce::from(header.nonce),
+                Payload {
+                    msg: encrypted,
+                    aad,
+                },
+            )
+            .map_err(|_| EncryptionError::Corrupt("Authentication failed".to_string()))?;
+
+        let text = String::from_utf8(decrypted).map_err(|e| e.to_string())?;
+        let mut table = Table::deserialize(text)?;
+        table.fingerprint = Some(fingerprint);
+        Ok(table)
+    }
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod test {
+
+    use super::{EncryptionError, HEADER_LENGTH, MAGIC};
+    use crate::column::Column;
+    use crate::hash::crc32;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn test_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "file-minidb-encrypted-{}-{}.table",
+            name,
+            std::process::id()
+        ))
+    }
+
+    fn create_test_table() -> Table {
+        let mut table = Table::new(vec![
+            Column::key("Name", ColumnType::String),
+            Column::new("Age", ColumnType::Integer),
+        ])
+        .unwrap();
+        table.insert(vec!["Peter".into(), 15.into()]).unwrap();
+        table.insert(vec!["Wendy".into(), 12.into()]).unwrap();
+        table
+    }
+
+    #[test]
+    fn roundtrip() {
+        let path = test_path("roundtrip");
+        let table = create_test_table();
+        table.write_encrypted_file(&path, "secret").unwrap();
+
+        let content = fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&content).contains("Peter"));
+        assert_eq!(Table::from_encrypted_file(&path, "secret"), Ok(table));
+        assert!(Table::from_file(&path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn wrong_key_and_corrupt_file() {
+        let path = test_path("errors");
+        create_test_table()
+            .write_encrypted_file(&path, "secret")
+            .unwrap();
+        let content = fs::read(&path).unwrap();
+
+        assert_eq!(
+            Table::from_encrypted_file(&path, "guess"),
+            Err(EncryptionError::WrongKey)
+        );
+
+        for index in [10, HEADER_LENGTH - 1, HEADER_LENGTH + 3, content.len() - 1] {
+            let mut corrupt = content.clone();
+            corrupt[index] ^= 1;
+            fs::write(&path, &corrupt).unwrap();
+            assert!(matches!(
+                Table::from_encrypted_file(&path, "secret"),
+                Err(EncryptionError::Corrupt(_))
+            ));
+        }
+
+        // A memory cost above the maximum with a recomputed checksum
+        let mut expensive = content.clone();
+        expensive[MAGIC.len() + 1..MAGIC.len() + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+        let checksum = crc32(&expensive[..HEADER_LENGTH - 4]);
+        expensive[HEADER_LENGTH - 4..HEADER_LENGTH].copy_from_slice(&checksum.to_le_bytes());
+        fs::write(&path, &expensive).unwrap();
+        assert_eq!(
+            Table::from_encrypted_file(&path, "secret"),
+            Err(EncryptionError::Corrupt(
+                "Key derivation costs too high".to_string()
+            ))
+        );
+
+        fs::write(&path, &content[..HEADER_LENGTH - 1]).unwrap();
+        assert!(matches!(
+            Table::from_encrypted_file(&path, "secret"),
+            Err(EncryptionError::Corrupt(_))
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
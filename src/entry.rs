@@ -1,10 +1,12 @@
 use crate::column::Column;
+use crate::types::ColumnType;
 use crate::values::Value;
 
 use std::collections::HashSet;
 use std::fmt;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {
     pub(crate) values: Vec<(Column, Value)>,
 }
@@ -31,7 +33,7 @@ impl Entry {
             .collect()
     }
 
-    fn get_key_values(&self) -> Vec<Value> {
+    pub(crate) fn get_key_values(&self) -> Vec<Value> {
         self.values
             .iter()
             .filter(|v| v.0.is_key)
@@ -65,7 +67,7 @@ impl Entry {
         for (self_col, other_col) in self
             .get_key_columns()
             .into_iter()
-            .zip(other.get_key_columns().into_iter())
+            .zip(other.get_key_columns())
         {
             if self_col != other_col {
                 return false;
@@ -76,7 +78,7 @@ impl Entry {
         for (self_val, other_val) in self
             .get_key_values()
             .into_iter()
-            .zip(other.get_key_values().into_iter())
+            .zip(other.get_key_values())
         {
             if self_val != other_val {
                 return false;
@@ -86,6 +88,46 @@ impl Entry {
         true
     }
 
+    /// The value in the column with the name.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values
+            .iter()
+            .find(|(c, _)| c.name == name)
+            .map(|(_, v)| v)
+    }
+
+    /// The integer in the column with the name, None if it is null.
+    /// Will error if there is no such column or it is not an integer column.
+    pub fn get_i32(&self, name: &str) -> Result<Option<i32>, String> {
+        match self.get(name) {
+            Some(Value::Integer(i)) => Ok(Some(*i)),
+            Some(Value::Null(ColumnType::Integer)) => Ok(None),
+            Some(_) => Err(format!("Column {} is not an integer column", name)),
+            None => Err(format!("No column named {}", name)),
+        }
+    }
+
+    /// The string in the column with the name, None if it is null.
+    /// Will error if there is no such column or it is not a string column.
+    pub fn get_str(&self, name: &str) -> Result<Option<&str>, String> {
+        match self.get(name) {
+            Some(Value::String(s)) => Ok(Some(s)),
+            Some(Value::Null(ColumnType::String)) => Ok(None),
+            Some(_) => Err(format!("Column {} is not a string column", name)),
+            None => Err(format!("No column named {}", name)),
+        }
+    }
+
+    /// The columns of this entry, in order.
+    pub fn columns(&self) -> impl Iterator<Item = &Column> {
+        self.values.iter().map(|(c, _)| c)
+    }
+
+    /// The columns of this entry together with their values, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Column, &Value)> {
+        self.values.iter().map(|(c, v)| (c, v))
+    }
+
     /// Returns the values of this entry in the given order.
     /// Will error if the given columns is not a subset of the columns of this entry.
     pub fn get_values_in_order(&self, columns: &[Column]) -> Result<Vec<Value>, String> {
@@ -296,4 +338,34 @@ mod tests {
 
         assert_eq!(entry.get_key_values(), vec![])
     }
+
+    #[test]
+    fn get_by_name() {
+        use crate::types::ColumnType;
+        use crate::values::Value;
+
+        let name = crate::column::Column::key("Name", ColumnType::String);
+        let age = crate::column::Column::new("Age", ColumnType::Integer);
+        let entry = super::Entry::new(vec![
+            (name.clone(), Value::String("Peter".to_owned())),
+            (age.clone(), Value::Null(ColumnType::Integer)),
+        ]);
+
+        assert_eq!(entry.get("Name"), Some(&Value::String("Peter".to_owned())));
+        assert_eq!(entry.get("Height"), None);
+        assert_eq!(entry.get_str("Name"), Ok(Some("Peter")));
+        assert_eq!(entry.get_i32("Age"), Ok(None));
+        assert!(entry.get_i32("Name").is_err());
+        assert!(entry.get_str("Age").is_err());
+        assert!(entry.get_str("Height").is_err());
+
+        assert_eq!(entry.columns().collect::<Vec<_>>(), vec![&name, &age]);
+        assert_eq!(
+            entry
+                .iter()
+                .map(|(c, _)| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Name", "Age"]
+        );
+    }
 }
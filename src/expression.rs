@@ -0,0 +1,484 @@
+use crate::column::Column;
+use crate::entry::Entry;
+use crate::types::ColumnType;
+use crate::values::Value;
+
+use std::fmt;
+use std::ops;
+
+/// The binary operators that can be used in an `Expression`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Concat,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+/// A placeholder in an `Expression` that is replaced by a value before evaluation.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Parameter {
+    /// A parameter by position, starting at 0.
+    Positional(usize),
+    Named(String),
+}
+
+/// An expression that can be evaluated against an `Entry`.
+///
+/// Comparisons and logical operators evaluate to the integers `1` (true)
+/// and `0` (false), every integer other than `0` is considered true.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Expression {
+    Column(String),
+    Literal(Value),
+    Parameter(Parameter),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+    Not(Box<Expression>),
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Concat => "||",
+            Operator::Equal => "=",
+            Operator::NotEqual => "<>",
+            Operator::Less => "<",
+            Operator::LessEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterEqual => ">=",
+            Operator::And => "AND",
+            Operator::Or => "OR",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Parameter::Positional(i) => write!(f, "?{}", i + 1),
+            Parameter::Named(name) => write!(f, ":{}", name),
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Column(name) => {
+                if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    write!(f, "{}", name)
+                } else {
+                    write!(f, "\"{}\"", name.replace('"', "\"\""))
+                }
+            }
+            Expression::Literal(Value::String(s)) => write!(f, "'{}'", s.replace('\'', "''")),
+            Expression::Literal(v) => write!(f, "{}", v),
+            Expression::Parameter(p) => write!(f, "{}", p),
+            Expression::Binary(left, op, right) => write!(f, "({} {} {})", left, op, right),
+            Expression::Not(inner) => write!(f, "NOT {}", inner),
+        }
+    }
+}
+
+impl Expression {
+    /// References the value of the column with the given name.
+    pub fn column<T: AsRef<str>>(name: T) -> Expression {
+        Expression::Column(name.as_ref().to_string())
+    }
+
+    /// A constant value.
+    pub fn literal<T: Into<Value>>(value: T) -> Expression {
+        Expression::Literal(value.into())
+    }
+
+    /// A placeholder for the value at the given position, starting at 0.
+    pub fn positional(position: usize) -> Expression {
+        Expression::Parameter(Parameter::Positional(position))
+    }
+
+    /// A placeholder for the value with the given name.
+    pub fn named<T: AsRef<str>>(name: T) -> Expression {
+        Expression::Parameter(Parameter::Named(name.as_ref().to_string()))
+    }
+
+    fn binary(self, op: Operator, other: Expression) -> Expression {
+        Expression::Binary(Box::new(self), op, Box::new(other))
+    }
+
+    /// Concatenates the textual representation of both values.
+    pub fn concat(self, other: Expression) -> Expression {
+        self.binary(Operator::Concat, other)
+    }
+
+    pub fn equal(self, other: Expression) -> Expression {
+        self.binary(Operator::Equal, other)
+    }
+
+    pub fn not_equal(self, other: Expression) -> Expression {
+        self.binary(Operator::NotEqual, other)
+    }
+
+    pub fn less(self, other: Expression) -> Expression {
+        self.binary(Operator::Less, other)
+    }
+
+    pub fn less_equal(self, other: Expression) -> Expression {
+        self.binary(Operator::LessEqual, other)
+    }
+
+    pub fn greater(self, other: Expression) -> Expression {
+        self.binary(Operator::Greater, other)
+    }
+
+    pub fn greater_equal(self, other: Expression) -> Expression {
+        self.binary(Operator::GreaterEqual, other)
+    }
+
+    pub fn and(self, other: Expression) -> Expression {
+        self.binary(Operator::And, other)
+    }
+
+    pub fn or(self, other: Expression) -> Expression {
+        self.binary(Operator::Or, other)
+    }
+
+    /// Infers the type the expression evaluates to for entries with the given columns.
+    /// Will error if a column is unknown or an operator is applied to the wrong types.
+    pub fn get_type(&self, columns: &[Column]) -> Result<ColumnType, String> {
+        match self {
+            Expression::Column(name) => columns
+                .iter()
+                .find(|c| &c.name == name)
+                .map(|c| c.get_type())
+                .ok_or_else(|| format!("Unknown column: {}", name)),
+            Expression::Literal(v) => Ok(v.get_type()),
+            Expression::Parameter(p) => Err(format!("Unbound parameter: {}", p)),
+            Expression::Binary(left, op, right) => {
+                let left_type = left.get_type(columns)?;
+                let right_type = right.get_type(columns)?;
+                Operator::result_type(*op, left_type, right_type)
+            }
+            Expression::Not(inner) => match inner.get_type(columns)? {
+                ColumnType::Integer => Ok(ColumnType::Integer),
+                t => Err(format!("Cannot apply NOT to {:?}", t)),
+            },
+        }
+    }
+
+    /// Evaluates the expression with the values of the given entry.
+    pub fn evaluate(&self, entry: &Entry) -> Result<Value, String> {
+        match self {
+            Expression::Column(name) => entry
+                .values
+                .iter()
+                .find(|(c, _)| &c.name == name)
+                .map(|(_, v)| v.clone())
+                .ok_or_else(|| format!("Unknown column: {}", name)),
+            Expression::Literal(v) => Ok(v.clone()),
+            Expression::Parameter(p) => Err(format!("Unbound parameter: {}", p)),
+            Expression::Binary(left, op, right) => {
+                op.apply(left.evaluate(entry)?, right.evaluate(entry)?)
+            }
+            Expression::Not(inner) => match inner.evaluate(entry)? {
+                Value::Integer(i) => Ok(Value::Integer((i == 0) as i32)),
+                Value::Null(ColumnType::Integer) => Ok(Value::Null(ColumnType::Integer)),
+                v => Err(format!("Cannot apply NOT to {:?}", v.get_type())),
+            },
+        }
+    }
+
+    /// Evaluates the expression and interprets the result as a condition.
+    pub fn is_true(&self, entry: &Entry) -> Result<bool, String> {
+        match self.evaluate(entry)? {
+            Value::Integer(i) => Ok(i != 0),
+            Value::Null(ColumnType::Integer) => Ok(false),
+            v => Err(format!(
+                "Condition must be an Integer, got {:?}",
+                v.get_type()
+            )),
+        }
+    }
+}
+
+impl Operator {
+    fn result_type(self, left: ColumnType, right: ColumnType) -> Result<ColumnType, String> {
+        match self {
+            Operator::Concat => Ok(ColumnType::String),
+            Operator::Equal
+            | Operator::NotEqual
+            | Operator::Less
+            | Operator::LessEqual
+            | Operator::Greater
+            | Operator::GreaterEqual
+                if left == right =>
+            {
+                Ok(ColumnType::Integer)
+            }
+            Operator::Add
+            | Operator::Subtract
+            | Operator::Multiply
+            | Operator::Divide
+            | Operator::And
+            | Operator::Or
+                if left == ColumnType::Integer && right == ColumnType::Integer =>
+            {
+                Ok(ColumnType::Integer)
+            }
+            _ => Err(format!(
+                "Cannot apply {} to {:?} and {:?}",
+                self, left, right
+            )),
+        }
+    }
+
+    fn apply(self, left: Value, right: Value) -> Result<Value, String> {
+        let overflow = || format!("Integer overflow in {} {} {}", left, self, right);
+
+        // Null is unknown, AND and OR can still be decided by the other side.
+        if left.is_null() || right.is_null() {
+            let result_type = self.result_type(left.get_type(), right.get_type())?;
+            return match (self, &left, &right) {
+                (Operator::And, Value::Integer(0), _) | (Operator::And, _, Value::Integer(0)) => {
+                    Ok(Value::Integer(0))
+                }
+                (Operator::Or, Value::Integer(i), _) | (Operator::Or, _, Value::Integer(i))
+                    if *i != 0 =>
+                {
+                    Ok(Value::Integer(1))
+                }
+                _ => Ok(Value::Null(result_type)),
+            };
+        }
+
+        match (self, &left, &right) {
+            (Operator::Concat, _, _) => Ok(Value::String(format!("{}{}", left, right))),
+            (Operator::Add, Value::Integer(l), Value::Integer(r)) => {
+                l.checked_add(*r).map(Value::Integer).ok_or_else(overflow)
+            }
+            (Operator::Subtract, Value::Integer(l), Value::Integer(r)) => {
+                l.checked_sub(*r).map(Value::Integer).ok_or_else(overflow)
+            }
+            (Operator::Multiply, Value::Integer(l), Value::Integer(r)) => {
+                l.checked_mul(*r).map(Value::Integer).ok_or_else(overflow)
+            }
+            (Operator::Divide, Value::Integer(_), Value::Integer(0)) => {
+                Err("Division by zero".to_string())
+            }
+            (Operator::Divide, Value::Integer(l), Value::Integer(r)) => {
+                l.checked_div(*r).map(Value::Integer).ok_or_else(overflow)
+            }
+            (Operator::And, Value::Integer(l), Value::Integer(r)) => {
+                Ok(Value::Integer((*l != 0 && *r != 0) as i32))
+            }
+            (Operator::Or, Value::Integer(l), Value::Integer(r)) => {
+                Ok(Value::Integer((*l != 0 || *r != 0) as i32))
+            }
+            (op, _, _) => {
+                // Only comparisons are left, these need equal types.
+                op.result_type(left.get_type(), right.get_type())?;
+                let ordering = match (&left, &right) {
+                    (Value::Integer(l), Value::Integer(r)) => l.cmp(r),
+                    (Value::String(l), Value::String(r)) => l.cmp(r),
+                    _ => unreachable!(),
+                };
+                let result = match op {
+                    Operator::Equal => ordering.is_eq(),
+                    Operator::NotEqual => ordering.is_ne(),
+                    Operator::Less => ordering.is_lt(),
+                    Operator::LessEqual => ordering.is_le(),
+                    Operator::Greater => ordering.is_gt(),
+                    Operator::GreaterEqual => ordering.is_ge(),
+                    _ => unreachable!(),
+                };
+                Ok(Value::Integer(result as i32))
+            }
+        }
+    }
+}
+
+impl ops::Add for Expression {
+    type Output = Expression;
+
+    fn add(self, other: Expression) -> Expression {
+        self.binary(Operator::Add, other)
+    }
+}
+
+impl ops::Sub for Expression {
+    type Output = Expression;
+
+    fn sub(self, other: Expression) -> Expression {
+        self.binary(Operator::Subtract, other)
+    }
+}
+
+impl ops::Mul for Expression {
+    type Output = Expression;
+
+    fn mul(self, other: Expression) -> Expression {
+        self.binary(Operator::Multiply, other)
+    }
+}
+
+impl ops::Div for Expression {
+    type Output = Expression;
+
+    fn div(self, other: Expression) -> Expression {
+        self.binary(Operator::Divide, other)
+    }
+}
+
+impl ops::Not for Expression {
+    type Output = Expression;
+
+    fn not(self) -> Expression {
+        Expression::Not(Box::new(self))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Expression;
+    use crate::column::Column;
+    use crate::entry::Entry;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    fn create_test_columns() -> Vec<Column> {
+        vec![
+            Column::key("First", ColumnType::String),
+            Column::key("Last", ColumnType::String),
+            Column::new("Age", ColumnType::Integer),
+        ]
+    }
+
+    fn create_test_entry() -> Entry {
+        let values = vec!["Peter".into(), "Pan".into(), 15.into()];
+        Entry::new(create_test_columns().into_iter().zip(values).collect())
+    }
+
+    #[test]
+    fn evaluate_arithmetic() {
+        let expression = Expression::column("Age") * Expression::literal(12)
+            - Expression::literal(2) / Expression::literal(2);
+
+        assert_eq!(
+            expression.evaluate(&create_test_entry()),
+            Ok(Value::Integer(179))
+        );
+        assert_eq!(
+            expression.get_type(&create_test_columns()),
+            Ok(ColumnType::Integer)
+        );
+    }
+
+    #[test]
+    fn evaluate_concat() {
+        let expression = Expression::column("First")
+            .concat(Expression::literal(" "))
+            .concat(Expression::column("Last"));
+
+        assert_eq!(
+            expression.evaluate(&create_test_entry()),
+            Ok("Peter Pan".into())
+        );
+        assert_eq!(
+            expression.get_type(&create_test_columns()),
+            Ok(ColumnType::String)
+        );
+    }
+
+    #[test]
+    fn evaluate_comparison() {
+        let entry = create_test_entry();
+
+        let older = Expression::column("Age").greater(Expression::literal(10));
+        let named = Expression::column("Last").equal(Expression::literal("Hook"));
+
+        assert!(older.is_true(&entry).unwrap());
+        assert!(!named.is_true(&entry).unwrap());
+        assert!(older.clone().or(named.clone()).is_true(&entry).unwrap());
+        assert!(!older.and(named).is_true(&entry).unwrap());
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let columns = create_test_columns();
+
+        assert!((Expression::column("Age") + Expression::column("First"))
+            .get_type(&columns)
+            .is_err());
+        assert!(Expression::column("Age")
+            .less(Expression::literal("10"))
+            .get_type(&columns)
+            .is_err());
+        assert!(Expression::column("First").get_type(&columns).is_ok());
+        assert_eq!(
+            Expression::column("Height").get_type(&columns),
+            Err("Unknown column: Height".to_string())
+        );
+    }
+
+    #[test]
+    fn evaluation_errors() {
+        let entry = create_test_entry();
+
+        assert!((Expression::column("Age") / Expression::literal(0))
+            .evaluate(&entry)
+            .is_err());
+        assert!((Expression::literal(i32::MAX) + Expression::column("Age"))
+            .evaluate(&entry)
+            .is_err());
+    }
+
+    #[test]
+    fn evaluate_null() {
+        let entry = create_test_entry();
+        let null = || Expression::literal(Value::Null(ColumnType::Integer));
+        let yes = || Expression::literal(1);
+        let no = || Expression::literal(0);
+
+        assert_eq!(
+            (Expression::column("Age") + null()).evaluate(&entry),
+            Ok(Value::Null(ColumnType::Integer))
+        );
+        assert_eq!(
+            Expression::column("Age").equal(null()).is_true(&entry),
+            Ok(false)
+        );
+        assert_eq!(no().and(null()).evaluate(&entry), Ok(Value::Integer(0)));
+        assert_eq!(
+            yes().and(null()).evaluate(&entry),
+            Ok(Value::Null(ColumnType::Integer))
+        );
+        assert_eq!(null().or(yes()).evaluate(&entry), Ok(Value::Integer(1)));
+        assert_eq!((!null()).is_true(&entry), Ok(false));
+    }
+
+    #[test]
+    fn display() {
+        let expression = !(Expression::column("Last Name")
+            .equal(Expression::literal("O'Neil"))
+            .and(Expression::column("Age").greater_equal(Expression::literal(18))));
+
+        assert_eq!(
+            expression.to_string(),
+            r#"NOT (("Last Name" = 'O''Neil') AND (Age >= 18))"#
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Replaces the file with the contents, such that after a crash the file
+/// either has the old or the new contents.
+///
+/// The contents are written to a temporary file in the same directory, which
+/// is synced and renamed over the file. Afterwards the directory is synced so
+/// the rename is durable.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let temporary = temporary_path(path)?;
+
+    let result = write_synced(&temporary, contents)
+        .and_then(|_| fs::rename(&temporary, path).map_err(|e| e.to_string()));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+        return result;
+    }
+
+    sync_directory(path)
+}
+
+pub(crate) fn temporary_path(path: &Path) -> Result<PathBuf, String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("Not a file: {}", path.display()))?;
+
+    let mut temporary = name.to_os_string();
+    temporary.push(".tmp");
+    Ok(path.with_file_name(temporary))
+}
+
+fn write_synced(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+
+    file.write_all(contents).map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())
+}
+
+// Directories can only be opened and synced like this on unix.
+#[cfg(unix)]
+pub(crate) fn sync_directory(path: &Path) -> Result<(), String> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    File::open(directory)
+        .and_then(|d| d.sync_all())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn sync_directory(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
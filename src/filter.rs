@@ -0,0 +1,458 @@
+use crate::column::Column;
+use crate::entry::Entry;
+use crate::expression::{Expression, Operator, Parameter};
+use crate::query;
+use crate::table::Table;
+use crate::types::ColumnType;
+use crate::values::Value;
+
+/// A reusable filter for the entries of tables, which can contain positional
+/// (`?`) or named (`:name`) parameters.
+/// The types of the parameters are inferred from the columns they are
+/// compared to and checked when binding values to them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Filter {
+    predicate: Expression,
+    // The columns the predicate refers to.
+    columns: Vec<Column>,
+    // The parameters in order of their first use, `None` if any type is accepted.
+    parameters: Vec<(Parameter, Option<ColumnType>)>,
+}
+
+/// A `Filter` with values for all of its parameters.
+/// It can be applied to any table having the columns the filter refers to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoundFilter {
+    predicate: Expression,
+    columns: Vec<Column>,
+}
+
+impl Filter {
+    /// Creates a filter for tables with the given columns.
+    /// Will error if the predicate is not a valid condition for these columns,
+    /// or if the type of a parameter cannot be inferred.
+    pub fn new(predicate: Expression, columns: &[Column]) -> Result<Filter, String> {
+        let mut parameters = vec![];
+        infer(&predicate, columns, &mut parameters)?;
+
+        let positional = parameters
+            .iter()
+            .filter(|(p, _)| matches!(p, Parameter::Positional(_)))
+            .count();
+        if positional != 0 && positional != parameters.len() {
+            return Err("Positional and named parameters cannot be mixed".to_string());
+        }
+        for i in 0..positional {
+            if !parameters
+                .iter()
+                .any(|(p, _)| *p == Parameter::Positional(i))
+            {
+                return Err(format!(
+                    "Parameter {} is never used",
+                    Parameter::Positional(i)
+                ));
+            }
+        }
+
+        // Check the whole predicate with example values for the parameters.
+        let example = substitute(&predicate, &|p| {
+            Some(
+                match parameters.iter().find(|(other, _)| other == p).unwrap().1 {
+                    Some(ColumnType::Integer) => Value::Integer(0),
+                    _ => Value::String(String::new()),
+                },
+            )
+        });
+        if example.get_type(columns)? != ColumnType::Integer {
+            return Err(format!(
+                "Filter must be an Integer condition: {}",
+                predicate
+            ));
+        }
+
+        let mut referenced = vec![];
+        collect_columns(&predicate, &mut referenced);
+        let columns = columns
+            .iter()
+            .filter(|c| referenced.contains(&&c.name))
+            .cloned()
+            .collect();
+
+        Ok(Filter {
+            predicate,
+            columns,
+            parameters,
+        })
+    }
+
+    /// Parses a filter like `Age >= ? AND "Last Name" = ?` or
+    /// `Age >= :min_age` for tables with the given columns.
+    pub fn parse(predicate: &str, columns: &[Column]) -> Result<Filter, String> {
+        let predicate = query::parse_expression(predicate).map_err(|e| e.to_string())?;
+
+        Filter::new(predicate, columns)
+    }
+
+    pub fn get_parameters(&self) -> Vec<Parameter> {
+        self.parameters.iter().map(|(p, _)| p.clone()).collect()
+    }
+
+    /// Binds the values to the positional parameters, in order.
+    pub fn bind(&self, values: &[Value]) -> Result<BoundFilter, String> {
+        if self
+            .parameters
+            .iter()
+            .any(|(p, _)| matches!(p, Parameter::Named(_)))
+        {
+            return Err("The filter has named parameters".to_string());
+        }
+        if values.len() != self.parameters.len() {
+            return Err(format!(
+                "Expected {} parameters, got {}",
+                self.parameters.len(),
+                values.len()
+            ));
+        }
+
+        self.bind_with(|p| match p {
+            Parameter::Positional(i) => values.get(*i).cloned(),
+            Parameter::Named(_) => None,
+        })
+    }
+
+    /// Binds the values to the named parameters.
+    pub fn bind_named(&self, values: &[(&str, Value)]) -> Result<BoundFilter, String> {
+        for (name, _) in values {
+            if !self
+                .parameters
+                .iter()
+                .any(|(p, _)| *p == Parameter::Named(name.to_string()))
+            {
+                return Err(format!("Unknown parameter: :{}", name));
+            }
+        }
+
+        self.bind_with(|p| match p {
+            Parameter::Named(name) => values
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone()),
+            Parameter::Positional(_) => None,
+        })
+    }
+
+    fn bind_with<F: Fn(&Parameter) -> Option<Value>>(
+        &self,
+        value_of: F,
+    ) -> Result<BoundFilter, String> {
+        for (parameter, expected) in &self.parameters {
+            match (value_of(parameter), expected) {
+                (None, _) => return Err(format!("No value given for parameter {}", parameter)),
+                (Some(value), Some(expected)) if value.get_type() != *expected => {
+                    return Err(format!(
+                        "Parameter {} expects {:?}, got {:?}",
+                        parameter,
+                        expected,
+                        value.get_type()
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(BoundFilter {
+            predicate: substitute(&self.predicate, &value_of),
+            columns: self.columns.clone(),
+        })
+    }
+}
+
+impl BoundFilter {
+    /// The predicate with the values in place of the parameters.
+    /// Can for example be used in a `Pipeline`.
+    pub fn get_predicate(&self) -> &Expression {
+        &self.predicate
+    }
+
+    /// Whether the entry passes the filter.
+    pub fn matches(&self, entry: &Entry) -> Result<bool, String> {
+        self.predicate.is_true(entry)
+    }
+
+    /// Returns a table with the entries of the given table passing the filter.
+    /// Will error if the table does not have the columns the filter refers to.
+    pub fn apply(&self, table: &Table) -> Result<Table, String> {
+        for column in &self.columns {
+            if !table
+                .columns
+                .iter()
+                .any(|c| c.name == column.name && c.get_type() == column.get_type())
+            {
+                return Err(format!(
+                    "Table has no column {} of type {:?}",
+                    column.name,
+                    column.get_type()
+                ));
+            }
+        }
+
+        let mut entries = vec![];
+        for entry in &table.entries {
+            if self.matches(entry)? {
+                entries.push(entry.clone());
+            }
+        }
+
+        Ok(Table {
+            entries,
+            ..Table::new(table.columns.clone())?
+        })
+    }
+}
+
+// Infers the type of the expression, `None` if it is a parameter without a known type.
+// The types of the parameters are recorded in `parameters`.
+fn infer(
+    expression: &Expression,
+    columns: &[Column],
+    parameters: &mut Vec<(Parameter, Option<ColumnType>)>,
+) -> Result<Option<ColumnType>, String> {
+    match expression {
+        Expression::Parameter(p) => match parameters.iter().find(|(other, _)| other == p) {
+            Some((_, t)) => Ok(t.clone()),
+            None => {
+                parameters.push((p.clone(), None));
+                Ok(None)
+            }
+        },
+        Expression::Column(_) | Expression::Literal(_) => expression.get_type(columns).map(Some),
+        Expression::Not(inner) => {
+            expect(inner, ColumnType::Integer, columns, parameters)?;
+            Ok(Some(ColumnType::Integer))
+        }
+        Expression::Binary(left, op, right) => match op {
+            Operator::Concat => {
+                infer(left, columns, parameters)?;
+                infer(right, columns, parameters)?;
+                Ok(Some(ColumnType::String))
+            }
+            Operator::Add
+            | Operator::Subtract
+            | Operator::Multiply
+            | Operator::Divide
+            | Operator::And
+            | Operator::Or => {
+                expect(left, ColumnType::Integer, columns, parameters)?;
+                expect(right, ColumnType::Integer, columns, parameters)?;
+                Ok(Some(ColumnType::Integer))
+            }
+            _ => {
+                match (
+                    infer(left, columns, parameters)?,
+                    infer(right, columns, parameters)?,
+                ) {
+                    (None, None) => {
+                        return Err(format!(
+                            "Cannot infer the parameter types in {}",
+                            expression
+                        ))
+                    }
+                    (None, Some(t)) => expect(left, t, columns, parameters)?,
+                    (Some(t), None) => expect(right, t, columns, parameters)?,
+                    _ => {}
+                }
+                Ok(Some(ColumnType::Integer))
+            }
+        },
+    }
+}
+
+// Infers the type of the expression and records the expected type for parameters.
+fn expect(
+    expression: &Expression,
+    expected: ColumnType,
+    columns: &[Column],
+    parameters: &mut Vec<(Parameter, Option<ColumnType>)>,
+) -> Result<(), String> {
+    if let Expression::Parameter(p) = expression {
+        infer(expression, columns, parameters)?;
+        let entry = parameters.iter_mut().find(|(other, _)| other == p).unwrap();
+        match &entry.1 {
+            Some(t) if *t != expected => {
+                return Err(format!(
+                    "Parameter {} is used as {:?} and {:?}",
+                    p, t, expected
+                ))
+            }
+            _ => entry.1 = Some(expected),
+        }
+        return Ok(());
+    }
+
+    infer(expression, columns, parameters)?;
+    Ok(())
+}
+
+// Replaces the parameters the function returns a value for.
+fn substitute<F: Fn(&Parameter) -> Option<Value>>(
+    expression: &Expression,
+    value_of: &F,
+) -> Expression {
+    match expression {
+        Expression::Parameter(p) => match value_of(p) {
+            Some(value) => Expression::Literal(value),
+            None => expression.clone(),
+        },
+        Expression::Column(_) | Expression::Literal(_) => expression.clone(),
+        Expression::Binary(left, op, right) => Expression::Binary(
+            Box::new(substitute(left, value_of)),
+            *op,
+            Box::new(substitute(right, value_of)),
+        ),
+        Expression::Not(inner) => Expression::Not(Box::new(substitute(inner, value_of))),
+    }
+}
+
+fn collect_columns<'a>(expression: &'a Expression, columns: &mut Vec<&'a String>) {
+    match expression {
+        Expression::Column(name) => columns.push(name),
+        Expression::Literal(_) | Expression::Parameter(_) => {}
+        Expression::Binary(left, _, right) => {
+            collect_columns(left, columns);
+            collect_columns(right, columns);
+        }
+        Expression::Not(inner) => collect_columns(inner, columns),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Filter;
+    use crate::column::Column;
+    use crate::expression::Expression;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    fn create_test_table() -> Table {
+        let column1 = Column::key("Name", ColumnType::String);
+        let column2 = Column::new("Age", ColumnType::Integer);
+        let mut table = Table::new(vec![column1, column2]).unwrap();
+
+        table.insert(vec!["Peter".into(), 15.into()]).unwrap();
+        table.insert(vec!["Wendy".into(), 12.into()]).unwrap();
+        table.insert(vec!["Hook".into(), 40.into()]).unwrap();
+
+        table
+    }
+
+    fn names(table: &Table) -> Vec<Value> {
+        table
+            .get_entries()
+            .iter()
+            .map(|e| e.get_values()[0].clone())
+            .collect()
+    }
+
+    #[test]
+    fn bind_positional() {
+        let table = create_test_table();
+
+        let filter = Filter::parse("Age >= ? AND Name <> ?", &table.columns).unwrap();
+        let bound = filter.bind(&[13.into(), "Hook".into()]).unwrap();
+
+        assert_eq!(names(&bound.apply(&table).unwrap()), vec!["Peter".into()]);
+        assert_eq!(
+            bound.get_predicate(),
+            &Expression::column("Age")
+                .greater_equal(Expression::literal(13))
+                .and(Expression::column("Name").not_equal(Expression::literal("Hook")))
+        );
+    }
+
+    #[test]
+    fn bind_named() {
+        let table = create_test_table();
+
+        let filter = Filter::new(
+            Expression::named("min")
+                .less_equal(Expression::column("Age"))
+                .and(Expression::column("Age").less_equal(Expression::named("max"))),
+            &table.columns,
+        )
+        .unwrap();
+        let bound = filter
+            .bind_named(&[("max", 20.into()), ("min", 13.into())])
+            .unwrap();
+
+        assert_eq!(names(&bound.apply(&table).unwrap()), vec!["Peter".into()]);
+        assert!(filter.bind_named(&[("min", 13.into())]).is_err());
+        assert!(filter
+            .bind_named(&[("min", 1.into()), ("max", 2.into()), ("mid", 3.into())])
+            .is_err());
+    }
+
+    #[test]
+    fn bind_checks_types() {
+        let table = create_test_table();
+
+        let filter = Filter::parse("Name = :name OR Age * 2 > :age", &table.columns).unwrap();
+
+        assert_eq!(
+            filter.bind_named(&[("name", 15.into()), ("age", 15.into())]),
+            Err("Parameter :name expects String, got Integer".to_string())
+        );
+        assert!(filter
+            .bind_named(&[("name", "Wendy".into()), ("age", "15".into())])
+            .is_err());
+        assert!(filter
+            .bind_named(&[("name", "Wendy".into()), ("age", 15.into())])
+            .is_ok());
+    }
+
+    #[test]
+    fn reuse_for_multiple_tables() {
+        let table = create_test_table();
+        let mut other = Table::new(vec![
+            Column::new("Age", ColumnType::Integer),
+            Column::key("Id", ColumnType::Integer),
+        ])
+        .unwrap();
+        other.insert(vec![50.into(), 1.into()]).unwrap();
+        other.insert(vec![5.into(), 2.into()]).unwrap();
+
+        let bound = Filter::parse("Age > ?", &table.columns)
+            .unwrap()
+            .bind(&[14.into()])
+            .unwrap();
+
+        assert_eq!(bound.apply(&table).unwrap().get_entries().len(), 2);
+        assert_eq!(
+            bound.apply(&other).unwrap().get_entries()[0].get_values(),
+            vec![50.into(), 1.into()]
+        );
+
+        let by_name = Filter::parse("Name = ?", &table.columns)
+            .unwrap()
+            .bind(&["Hook".into()])
+            .unwrap();
+        assert_eq!(
+            by_name.apply(&other),
+            Err("Table has no column Name of type String".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_filters() {
+        let columns = create_test_table().columns;
+
+        assert!(Filter::parse("? = ?", &columns).is_err());
+        assert!(Filter::parse("Age = ? AND Name = :name", &columns).is_err());
+        assert!(Filter::parse("Age = ?2", &columns).is_err());
+        assert!(Filter::parse("Height = ?", &columns).is_err());
+        assert!(Filter::parse("Name || ?", &columns).is_err());
+        assert!(Filter::parse("Age = ?1 AND Name = ?1", &columns).is_err());
+        assert!(Filter::parse("Age = ", &columns).is_err());
+    }
+}
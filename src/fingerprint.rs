@@ -0,0 +1,410 @@
+use crate::entry::Entry;
+use crate::file;
+use crate::hash::fnv1a;
+use crate::serializer::Serializable;
+use crate::table::Table;
+use crate::values::Value;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File, Metadata};
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Identifies the contents of a table file at one point in time.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    // None if the platform does not record modification times.
+    pub(crate) modified: Option<SystemTime>,
+    pub(crate) size: u64,
+    pub(crate) hash: u64,
+}
+
+impl Fingerprint {
+    fn new(metadata: &Metadata, content: &[u8]) -> Fingerprint {
+        Fingerprint {
+            modified: metadata.modified().ok(),
+            size: metadata.len(),
+            hash: fnv1a(content),
+        }
+    }
+
+    // None if the file does not exist.
+    fn of_file(path: &Path) -> Result<Option<Fingerprint>, String> {
+        match read_file(path) {
+            Ok((_, fingerprint)) => Ok(Some(fingerprint)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// An error while saving a table only if its file is unchanged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConflictError {
+    /// The file was changed since the table was loaded or saved.
+    Changed,
+    Error(String),
+}
+
+impl fmt::Display for ConflictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictError::Changed => write!(f, "File changed since the table was loaded"),
+            ConflictError::Error(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<String> for ConflictError {
+    fn from(e: String) -> Self {
+        ConflictError::Error(e)
+    }
+}
+
+impl Table {
+    /// The fingerprint of the file the table was loaded from or last saved to
+    /// with `save_if_unchanged`.
+    pub fn get_fingerprint(&self) -> Option<&Fingerprint> {
+        self.fingerprint.as_ref()
+    }
+
+    /// Writes the table to the file, like `write_file`, but only if the file
+    /// was not changed since the table was loaded from it or last saved to it.
+    /// A table not loaded from a file is only written if the file does not exist.
+    ///
+    /// The replaced file is kept next to the file, named like it with `.base`
+    /// appended, for `reload_and_merge` of the tables loaded from it.
+    ///
+    /// The check is not atomic with the write, use `LockedTable` to keep other
+    /// processes out completely.
+    pub fn save_if_unchanged(&mut self, path: &Path) -> Result<(), ConflictError> {
+        let fingerprint = Fingerprint::of_file(path)?;
+        if fingerprint.as_ref() != self.get_fingerprint() {
+            return Err(ConflictError::Changed);
+        }
+
+        if fingerprint.is_some() {
+            keep_base(path)?;
+        }
+        let content = self.serialize_current();
+        file::write_atomic(path, content.as_bytes())?;
+
+        let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+        self.fingerprint = Some(Fingerprint::new(&metadata, content.as_bytes()));
+
+        Ok(())
+    }
+
+    /// Loads the file again and merges its changes with the changes of this
+    /// table since it was loaded, so that `save_if_unchanged` can succeed.
+    ///
+    /// Entries are matched by their keys, or by all values in tables without
+    /// keys. Entries added, changed or removed on only one side are taken from
+    /// that side. If an entry was changed or removed on both sides, this table
+    /// wins: an entry changed here is kept even if it was removed from the
+    /// file, and an entry removed here stays removed even if it was changed in
+    /// the file.
+    ///
+    /// The entries this table was loaded with are read from the file kept by
+    /// `save_if_unchanged`. Will error if the file was replaced in another way,
+    /// e.g. with `write_file`, or more than once since the table was loaded.
+    pub fn reload_and_merge(&mut self, path: &Path) -> Result<(), String> {
+        let loaded = Table::from_file(path)?;
+        if loaded.columns != self.columns {
+            return Err(format!(
+                "Columns of the table file changed: {}",
+                path.display()
+            ));
+        }
+
+        let base = self.merge_base(path, &loaded)?;
+        let is_unchanged =
+            |entry: &Entry| base.get(&identity(self, entry)) == Some(&entry_hash(entry));
+
+        let loaded_entries: HashMap<Vec<Value>, &Entry> = loaded
+            .entries
+            .iter()
+            .map(|e| (identity(&loaded, e), e))
+            .collect();
+        let mut merged = Table::new(self.columns.clone())?;
+        let mut merged_identities = HashSet::new();
+
+        for entry in &self.entries {
+            let id = identity(self, entry);
+            let result = match loaded_entries.get(&id) {
+                Some(loaded_entry) if is_unchanged(entry) => Some(*loaded_entry),
+                Some(_) => Some(entry),
+                // Removed from the file, unless it was changed here.
+                None if is_unchanged(entry) => None,
+                None => Some(entry),
+            };
+            if let Some(result) = result {
+                merged.insert(result.get_values())?;
+                merged_identities.insert(id);
+            }
+        }
+        for entry in &loaded.entries {
+            let id = identity(&loaded, entry);
+            // Entries known before were either merged above or removed here.
+            if !merged_identities.contains(&id) && !base.contains_key(&id) {
+                merged.insert(entry.get_values())?;
+            }
+        }
+
+        self.entries = merged.entries;
+        self.fingerprint = loaded.fingerprint;
+
+        Ok(())
+    }
+
+    // The hash of every entry by its identity in the file the table was
+    // loaded from, to find what changed since.
+    fn merge_base(&self, path: &Path, loaded: &Table) -> Result<HashMap<Vec<Value>, u64>, String> {
+        let kept;
+        let base = match &self.fingerprint {
+            None => return Ok(HashMap::new()),
+            Some(fingerprint) if loaded.fingerprint.as_ref() == Some(fingerprint) => loaded,
+            Some(fingerprint) => {
+                let base_path = base_path(path);
+                let missing = || {
+                    format!(
+                        "The file the table was loaded from was not kept by save_if_unchanged, \
+                         it was replaced in another way or more than once: {}",
+                        path.display()
+                    )
+                };
+                let (content, base_fingerprint) = match read_file(&base_path) {
+                    Ok(base) => base,
+                    Err(e) if e.kind() == ErrorKind::NotFound => return Err(missing()),
+                    Err(e) => return Err(e.to_string()),
+                };
+                // The kept file may be a copy with another modification time.
+                if (base_fingerprint.size, base_fingerprint.hash)
+                    != (fingerprint.size, fingerprint.hash)
+                {
+                    return Err(missing());
+                }
+                kept = Table::deserialize_file(content, &base_path)?.0;
+                &kept
+            }
+        };
+
+        Ok(base
+            .entries
+            .iter()
+            .map(|e| (identity(base, e), entry_hash(e)))
+            .collect())
+    }
+}
+
+/// Reads a table file together with its fingerprint.
+pub(crate) fn read_file(path: &Path) -> std::io::Result<(Vec<u8>, Fingerprint)> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+
+    let mut content = vec![];
+    file.read_to_end(&mut content)?;
+
+    let fingerprint = Fingerprint::new(&metadata, &content);
+    Ok((content, fingerprint))
+}
+
+// Keeps the file as it is as the merge base, see `save_if_unchanged`.
+// A hard link shares the contents with the file, which is replaced afterwards.
+fn keep_base(path: &Path) -> Result<(), String> {
+    let base_path = base_path(path);
+    match fs::remove_file(&base_path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.to_string()),
+        _ => {}
+    }
+    if fs::hard_link(path, &base_path).is_err() {
+        fs::copy(path, &base_path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn base_path(path: &Path) -> PathBuf {
+    let mut base = path.as_os_str().to_os_string();
+    base.push(".base");
+    PathBuf::from(base)
+}
+
+fn identity(table: &Table, entry: &Entry) -> Vec<Value> {
+    if table.columns.iter().any(|c| c.is_key) {
+        entry.get_key_values()
+    } else {
+        entry.get_values()
+    }
+}
+
+fn entry_hash(entry: &Entry) -> u64 {
+    fnv1a(entry.serialize().as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{base_path, ConflictError};
+    use crate::column::Column;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn test_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "file-minidb-fingerprint-{}-{}.table",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn create_test_table() -> Table {
+        let mut table = Table::new(vec![
+            Column::key("Name", ColumnType::String),
+            Column::new("Age", ColumnType::Integer),
+        ])
+        .unwrap();
+        table.insert(vec!["Peter".into(), 15.into()]).unwrap();
+        table.insert(vec!["Wendy".into(), 12.into()]).unwrap();
+        table.insert(vec!["John".into(), 10.into()]).unwrap();
+        table
+    }
+
+    fn ages(table: &Table) -> Vec<(Value, Value)> {
+        let mut ages: Vec<(Value, Value)> = table
+            .get_entries()
+            .iter()
+            .map(|e| (e.get_values()[0].clone(), e.get_values()[1].clone()))
+            .collect();
+        ages.sort();
+        ages
+    }
+
+    #[test]
+    fn save_if_unchanged() {
+        let path = test_path("save");
+        let mut table = create_test_table();
+        table.save_if_unchanged(&path).unwrap();
+        assert!(table.get_fingerprint().is_some());
+
+        let mut loaded = Table::from_file(&path).unwrap();
+        assert_eq!(loaded.get_fingerprint(), table.get_fingerprint());
+        assert_eq!(loaded, table);
+
+        loaded.remove(vec!["John".into()]);
+        loaded.save_if_unchanged(&path).unwrap();
+        loaded.save_if_unchanged(&path).unwrap();
+
+        table.remove(vec!["Wendy".into()]);
+        assert_eq!(table.save_if_unchanged(&path), Err(ConflictError::Changed));
+        assert_eq!(Table::from_file(&path).unwrap(), loaded);
+
+        assert_eq!(
+            create_test_table().save_if_unchanged(&path),
+            Err(ConflictError::Changed)
+        );
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(base_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn reload_and_merge() {
+        let path = test_path("merge");
+        create_test_table().write_file(&path).unwrap();
+
+        let mut mine = Table::from_file(&path).unwrap();
+        let mut theirs = Table::from_file(&path).unwrap();
+
+        theirs.remove(vec!["John".into()]);
+        theirs
+            .update(vec!["Wendy".into()], vec!["Wendy".into(), 13.into()])
+            .unwrap();
+        theirs.insert(vec!["Tinker".into(), 1.into()]).unwrap();
+        theirs.save_if_unchanged(&path).unwrap();
+
+        mine.update(vec!["Peter".into()], vec!["Peter".into(), 16.into()])
+            .unwrap();
+        mine.insert(vec!["Michael".into(), 8.into()]).unwrap();
+        assert_eq!(mine.save_if_unchanged(&path), Err(ConflictError::Changed));
+
+        mine.reload_and_merge(&path).unwrap();
+        assert_eq!(
+            ages(&mine),
+            vec![
+                ("Michael".into(), 8.into()),
+                ("Peter".into(), 16.into()),
+                ("Tinker".into(), 1.into()),
+                ("Wendy".into(), 13.into()),
+            ]
+        );
+        mine.save_if_unchanged(&path).unwrap();
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(base_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn merge_removed_and_changed() {
+        let path = test_path("removed_and_changed");
+        create_test_table().write_file(&path).unwrap();
+
+        let mut mine = Table::from_file(&path).unwrap();
+        let mut theirs = Table::from_file(&path).unwrap();
+
+        theirs.remove(vec!["Peter".into()]);
+        theirs
+            .update(vec!["Wendy".into()], vec!["Wendy".into(), 13.into()])
+            .unwrap();
+        theirs.save_if_unchanged(&path).unwrap();
+
+        mine.update(vec!["Peter".into()], vec!["Peter".into(), 16.into()])
+            .unwrap();
+        mine.remove(vec!["Wendy".into()]);
+
+        mine.reload_and_merge(&path).unwrap();
+        assert_eq!(
+            ages(&mine),
+            vec![("John".into(), 10.into()), ("Peter".into(), 16.into())]
+        );
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(base_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn merge_base_replaced() {
+        let path = test_path("replaced");
+        create_test_table().write_file(&path).unwrap();
+
+        let mut mine = Table::from_file(&path).unwrap();
+        let mut theirs = Table::from_file(&path).unwrap();
+        theirs.remove(vec!["John".into()]);
+        theirs.save_if_unchanged(&path).unwrap();
+        theirs.remove(vec!["Wendy".into()]);
+        theirs.save_if_unchanged(&path).unwrap();
+
+        mine.remove(vec!["Peter".into()]);
+        assert!(mine.reload_and_merge(&path).is_err());
+        assert_eq!(mine.get_entries().len(), 2);
+
+        // Not kept if the file was replaced with `write_file`.
+        let mut mine = Table::from_file(&path).unwrap();
+        fs::remove_file(base_path(&path)).unwrap();
+        create_test_table().write_file(&path).unwrap();
+        mine.remove(vec!["Peter".into()]);
+        assert!(mine
+            .reload_and_merge(&path)
+            .unwrap_err()
+            .contains("replaced in another way"));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
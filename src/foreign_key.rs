@@ -0,0 +1,484 @@
+use crate::entry::Entry;
+use crate::table::Table;
+use crate::values::Value;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// What happens to referencing entries if the referenced entry is removed or
+/// its key is changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// The change is rejected.
+    Restrict,
+    /// Referencing entries are removed or get the new key.
+    Cascade,
+    /// The referencing columns are set to null.
+    SetNull,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Restrict => write!(f, "restrict"),
+            Action::Cascade => write!(f, "cascade"),
+            Action::SetNull => write!(f, "set null"),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Action, String> {
+        match str {
+            "restrict" => Ok(Action::Restrict),
+            "cascade" => Ok(Action::Cascade),
+            "set null" => Ok(Action::SetNull),
+            _ => Err(format!("Unknown foreign key action: {}", str)),
+        }
+    }
+}
+
+/// A reference from columns of one table to the key columns of another table.
+///
+/// The columns reference the key columns of the referenced table in the order
+/// in which these appear in the table. Entries with a null in any of the
+/// columns reference nothing.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ForeignKey {
+    pub(crate) table: String,
+    pub(crate) columns: Vec<String>,
+    pub(crate) referenced_table: String,
+    pub(crate) on_delete: Action,
+    pub(crate) on_update: Action,
+}
+
+impl fmt::Display for ForeignKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}({}) -> {}",
+            self.table,
+            self.columns.join(", "),
+            self.referenced_table
+        )
+    }
+}
+
+impl ForeignKey {
+    /// Creates a foreign key from the columns of the table to the keys of the
+    /// referenced table, which restricts removing and updating referenced entries.
+    pub fn new<T: AsRef<str>, U: AsRef<str>>(
+        table: T,
+        columns: &[&str],
+        referenced_table: U,
+    ) -> ForeignKey {
+        ForeignKey {
+            table: table.as_ref().to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            referenced_table: referenced_table.as_ref().to_string(),
+            on_delete: Action::Restrict,
+            on_update: Action::Restrict,
+        }
+    }
+
+    /// Sets the action done if a referenced entry is removed.
+    pub fn on_delete(mut self, action: Action) -> ForeignKey {
+        self.on_delete = action;
+        self
+    }
+
+    /// Sets the action done if the key of a referenced entry is changed.
+    pub fn on_update(mut self, action: Action) -> ForeignKey {
+        self.on_update = action;
+        self
+    }
+
+    /// Checks that the foreign key fits the columns of the tables.
+    pub(crate) fn validate(&self, tables: &HashMap<String, Table>) -> Result<(), String> {
+        let table = get_table(tables, &self.table)?;
+        let referenced = get_table(tables, &self.referenced_table)?;
+
+        let indices = self.column_indices(table)?;
+        let key_indices = key_indices(referenced);
+        if key_indices.len() != indices.len() {
+            return Err(format!(
+                "Foreign key {} has {} columns, but the referenced table has {} key columns",
+                self,
+                indices.len(),
+                key_indices.len()
+            ));
+        }
+
+        for (&index, &key_index) in indices.iter().zip(&key_indices) {
+            let column = &table.columns[index];
+            let key_column = &referenced.columns[key_index];
+            if column.get_type() != key_column.get_type() {
+                return Err(format!(
+                    "Foreign key {}: column {} is {:?}, but key column {} is {:?}",
+                    self,
+                    column.name,
+                    column.get_type(),
+                    key_column.name,
+                    key_column.get_type()
+                ));
+            }
+            if column.is_key
+                && (self.on_delete == Action::SetNull || self.on_update == Action::SetNull)
+            {
+                return Err(format!(
+                    "Foreign key {}: key column {} cannot be set to null",
+                    self, column.name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every entry of the table references an existing entry.
+    pub(crate) fn check(&self, tables: &HashMap<String, Table>) -> Result<(), String> {
+        self.check_entries(tables, &get_table(tables, &self.table)?.entries)
+    }
+
+    // Checks that the entries of the table reference existing entries,
+    // looking at every entry of the referenced table at most once.
+    fn check_entries(
+        &self,
+        tables: &HashMap<String, Table>,
+        entries: &[Entry],
+    ) -> Result<(), String> {
+        let table = get_table(tables, &self.table)?;
+        let referenced = get_table(tables, &self.referenced_table)?;
+
+        let indices = self.column_indices(table)?;
+        let references: Vec<Option<Vec<Value>>> =
+            entries.iter().map(|e| reference(e, &indices)).collect();
+        let mut missing: HashSet<&Vec<Value>> = references.iter().flatten().collect();
+        for entry in &referenced.entries {
+            if missing.is_empty() {
+                break;
+            }
+            missing.remove(&entry.get_key_values());
+        }
+
+        match entries
+            .iter()
+            .zip(&references)
+            .find(|(_, r)| r.as_ref().is_some_and(|r| missing.contains(r)))
+        {
+            Some((entry, _)) => Err(format!(
+                "Foreign key {} violated, no referenced entry for {}",
+                self, entry
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn column_indices(&self, table: &Table) -> Result<Vec<usize>, String> {
+        let mut indices = vec![];
+        for name in &self.columns {
+            let index = table
+                .columns
+                .iter()
+                .position(|c| &c.name == name)
+                .ok_or_else(|| format!("Unknown column: {}", name))?;
+            if indices.contains(&index) {
+                return Err(format!("Column used more than once: {}", name));
+            }
+            indices.push(index);
+        }
+        Ok(indices)
+    }
+}
+
+fn get_table<'a>(tables: &'a HashMap<String, Table>, name: &str) -> Result<&'a Table, String> {
+    tables
+        .get(name)
+        .ok_or_else(|| format!("Unknown table: {}", name))
+}
+
+fn key_indices(table: &Table) -> Vec<usize> {
+    (0..table.columns.len())
+        .filter(|&i| table.columns[i].is_key)
+        .collect()
+}
+
+// The referenced key of an entry, None if it references nothing.
+fn reference(entry: &Entry, indices: &[usize]) -> Option<Vec<Value>> {
+    let values: Vec<Value> = indices.iter().map(|&i| entry.values[i].1.clone()).collect();
+    if values.iter().any(|v| v.is_null()) {
+        None
+    } else {
+        Some(values)
+    }
+}
+
+/// Checks the foreign keys of the table for the given entries of it, without
+/// looking at its other entries.
+pub(crate) fn check_entries(
+    tables: &HashMap<String, Table>,
+    foreign_keys: &[ForeignKey],
+    table: &str,
+    entries: &[Entry],
+) -> Result<(), String> {
+    for foreign_key in foreign_keys.iter().filter(|f| f.table == table) {
+        foreign_key.check_entries(tables, entries)?;
+    }
+    Ok(())
+}
+
+/// Applies the delete actions of the foreign keys for entries removed from
+/// the table. Removing further entries on cascade applies their actions too.
+pub(crate) fn on_entries_removed(
+    tables: &mut HashMap<String, Table>,
+    foreign_keys: &[ForeignKey],
+    table: &str,
+    removed: Vec<Entry>,
+) -> Result<(), String> {
+    if removed.is_empty() {
+        return Ok(());
+    }
+    let keys: HashSet<Vec<Value>> = removed.iter().map(|e| e.get_key_values()).collect();
+
+    for foreign_key in foreign_keys.iter().filter(|f| f.referenced_table == table) {
+        let referencing = get_table(tables, &foreign_key.table)?;
+        let indices = foreign_key.column_indices(referencing)?;
+        let references = |e: &Entry| reference(e, &indices).is_some_and(|r| keys.contains(&r));
+
+        match foreign_key.on_delete {
+            Action::Restrict => {
+                if let Some(entry) = referencing.entries.iter().find(|e| references(e)) {
+                    return Err(format!(
+                        "Foreign key {} restricts removal, entry is referenced by {}",
+                        foreign_key, entry
+                    ));
+                }
+            }
+            Action::Cascade => {
+                let (gone, kept) = referencing.entries.iter().cloned().partition(references);
+                tables.get_mut(&foreign_key.table).unwrap().entries = kept;
+                on_entries_removed(tables, foreign_keys, &foreign_key.table, gone)?;
+            }
+            Action::SetNull => {
+                let changed: Vec<usize> = (0..referencing.entries.len())
+                    .filter(|&i| references(&referencing.entries[i]))
+                    .collect();
+                set_null(
+                    tables.get_mut(&foreign_key.table).unwrap(),
+                    &indices,
+                    &changed,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies the update actions of the foreign keys for entries of the table,
+/// whose key changed from the first to the second key.
+/// Changing further keys on cascade applies their actions too.
+pub(crate) fn on_keys_changed(
+    tables: &mut HashMap<String, Table>,
+    foreign_keys: &[ForeignKey],
+    table: &str,
+    changes: Vec<(Vec<Value>, Vec<Value>)>,
+) -> Result<(), String> {
+    let changes: HashMap<Vec<Value>, Vec<Value>> = changes
+        .into_iter()
+        .filter(|(old, new)| old != new)
+        .collect();
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    for foreign_key in foreign_keys.iter().filter(|f| f.referenced_table == table) {
+        let referencing = get_table(tables, &foreign_key.table)?;
+        let indices = foreign_key.column_indices(referencing)?;
+        let changed: Vec<(usize, Vec<Value>)> = (0..referencing.entries.len())
+            .filter_map(|i| {
+                let reference = reference(&referencing.entries[i], &indices)?;
+                changes.get(&reference).map(|new| (i, new.clone()))
+            })
+            .collect();
+        if changed.is_empty() {
+            continue;
+        }
+
+        match foreign_key.on_update {
+            Action::Restrict => {
+                return Err(format!(
+                    "Foreign key {} restricts update, entry is referenced by {}",
+                    foreign_key, referencing.entries[changed[0].0]
+                ));
+            }
+            Action::Cascade => {
+                let referencing = tables.get_mut(&foreign_key.table).unwrap();
+                let mut key_changes = vec![];
+                for (i, new) in changed {
+                    let old_key = referencing.entries[i].get_key_values();
+                    let mut values = referencing.entries[i].get_values();
+                    for (&index, value) in indices.iter().zip(new) {
+                        values[index] = value;
+                    }
+                    referencing.replace(i, values)?;
+                    key_changes.push((old_key, referencing.entries[i].get_key_values()));
+                }
+                on_keys_changed(tables, foreign_keys, &foreign_key.table, key_changes)?;
+            }
+            Action::SetNull => {
+                let changed: Vec<usize> = changed.into_iter().map(|(i, _)| i).collect();
+                set_null(
+                    tables.get_mut(&foreign_key.table).unwrap(),
+                    &indices,
+                    &changed,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The columns are never key columns, see `ForeignKey::validate`.
+fn set_null(table: &mut Table, indices: &[usize], entries: &[usize]) {
+    for &entry in entries {
+        for &index in indices {
+            let value = &mut table.entries[entry].values[index].1;
+            *value = Value::Null(value.get_type());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{on_entries_removed, on_keys_changed, Action, ForeignKey};
+    use crate::column::Column;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    use std::collections::HashMap;
+
+    fn create_test_tables() -> HashMap<String, Table> {
+        let mut customers = Table::new(vec![
+            Column::key("Id", ColumnType::Integer),
+            Column::new("Name", ColumnType::String),
+        ])
+        .unwrap();
+        customers.insert(vec![1.into(), "Peter".into()]).unwrap();
+        customers.insert(vec![2.into(), "Wendy".into()]).unwrap();
+
+        let mut orders = Table::new(vec![
+            Column::key("Number", ColumnType::Integer),
+            Column::new("Customer", ColumnType::Integer),
+        ])
+        .unwrap();
+        orders.insert(vec![10.into(), 1.into()]).unwrap();
+        orders.insert(vec![11.into(), 1.into()]).unwrap();
+        orders.insert(vec![12.into(), 2.into()]).unwrap();
+
+        let mut tables = HashMap::new();
+        tables.insert("customers".to_string(), customers);
+        tables.insert("orders".to_string(), orders);
+        tables
+    }
+
+    fn customer_references(tables: &HashMap<String, Table>) -> Vec<Value> {
+        tables["orders"]
+            .get_entries()
+            .iter()
+            .map(|e| e.get_values()[1].clone())
+            .collect()
+    }
+
+    #[test]
+    fn validate() {
+        let tables = create_test_tables();
+
+        assert!(ForeignKey::new("orders", &["Customer"], "customers")
+            .validate(&tables)
+            .is_ok());
+        assert!(ForeignKey::new("orders", &["Unknown"], "customers")
+            .validate(&tables)
+            .is_err());
+        assert!(
+            ForeignKey::new("orders", &["Number", "Customer"], "customers")
+                .validate(&tables)
+                .is_err()
+        );
+        assert!(ForeignKey::new("orders", &["Number"], "customers")
+            .on_delete(Action::SetNull)
+            .validate(&tables)
+            .is_err());
+        assert!(ForeignKey::new("customers", &["Name"], "orders")
+            .validate(&tables)
+            .is_err());
+    }
+
+    #[test]
+    fn check() {
+        let mut tables = create_test_tables();
+        let foreign_key = ForeignKey::new("orders", &["Customer"], "customers");
+        assert!(foreign_key.check(&tables).is_ok());
+
+        let orders = tables.get_mut("orders").unwrap();
+        orders
+            .insert(vec![13.into(), Value::Null(ColumnType::Integer)])
+            .unwrap();
+        assert!(foreign_key.check(&tables).is_ok());
+
+        let orders = tables.get_mut("orders").unwrap();
+        orders.insert(vec![14.into(), 3.into()]).unwrap();
+        assert!(foreign_key.check(&tables).is_err());
+    }
+
+    #[test]
+    fn remove_actions() {
+        let foreign_key = ForeignKey::new("orders", &["Customer"], "customers");
+        let removed = create_test_tables()["customers"].get_entries()[..1].to_vec();
+
+        let mut tables = create_test_tables();
+        let restrict = vec![foreign_key.clone()];
+        assert!(on_entries_removed(&mut tables, &restrict, "customers", removed.clone()).is_err());
+
+        let mut tables = create_test_tables();
+        let cascade = vec![foreign_key.clone().on_delete(Action::Cascade)];
+        on_entries_removed(&mut tables, &cascade, "customers", removed.clone()).unwrap();
+        assert_eq!(customer_references(&tables), vec![Value::Integer(2)]);
+
+        let mut tables = create_test_tables();
+        let set_null = vec![foreign_key.on_delete(Action::SetNull)];
+        on_entries_removed(&mut tables, &set_null, "customers", removed).unwrap();
+        assert_eq!(
+            customer_references(&tables),
+            vec![
+                Value::Null(ColumnType::Integer),
+                Value::Null(ColumnType::Integer),
+                Value::Integer(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn update_actions() {
+        let foreign_key = ForeignKey::new("orders", &["Customer"], "customers");
+        let changes = vec![(vec![Value::Integer(1)], vec![Value::Integer(5)])];
+
+        let mut tables = create_test_tables();
+        let restrict = vec![foreign_key.clone()];
+        assert!(on_keys_changed(&mut tables, &restrict, "customers", changes.clone()).is_err());
+
+        let mut tables = create_test_tables();
+        let cascade = vec![foreign_key.on_update(Action::Cascade)];
+        on_keys_changed(&mut tables, &cascade, "customers", changes).unwrap();
+        assert_eq!(
+            customer_references(&tables),
+            vec![Value::Integer(5), Value::Integer(5), Value::Integer(2)]
+        );
+    }
+}
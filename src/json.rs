@@ -0,0 +1,775 @@
+use crate::column::Column;
+use crate::table::Table;
+use crate::types::ColumnType;
+use crate::values::Value;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Write;
+
+// Deeper documents are rejected, so that parsing cannot overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+// The `format` of tables written with schema.
+const FORMAT: &str = "file-minidb";
+
+/// How the entries of a table are laid out in JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JsonLayout {
+    /// An array with an object for every entry, keyed by column name.
+    Rows,
+    /// An object with an array of the values of every column, keyed by column name.
+    Columns,
+}
+
+/// How a table is written as JSON, see `Table::to_json`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonOptions {
+    pub(crate) layout: JsonLayout,
+    pub(crate) schema: bool,
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        JsonOptions::new()
+    }
+}
+
+impl JsonOptions {
+    /// Entries as rows, without schema.
+    pub fn new() -> JsonOptions {
+        JsonOptions {
+            layout: JsonLayout::Rows,
+            schema: false,
+        }
+    }
+
+    pub fn layout(mut self, layout: JsonLayout) -> JsonOptions {
+        self.layout = layout;
+        self
+    }
+
+    /// Wraps the entries in an object together with the columns.
+    pub fn schema(mut self, schema: bool) -> JsonOptions {
+        self.schema = schema;
+        self
+    }
+}
+
+/// An entry that does not fit the columns of the table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonRowError {
+    /// The position of the entry, starting at 0.
+    pub index: usize,
+    pub error: String,
+}
+
+/// An error while reading a table from JSON.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JsonError {
+    /// The entries that do not fit the columns, the others are valid.
+    Rows(Vec<JsonRowError>),
+    Error(String),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Rows(rows) => {
+                write!(f, "Invalid entries:")?;
+                for row in rows {
+                    write!(f, " {}: {};", row.index, row.error)?;
+                }
+                Ok(())
+            }
+            JsonError::Error(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<String> for JsonError {
+    fn from(e: String) -> Self {
+        JsonError::Error(e)
+    }
+}
+
+impl Table {
+    /// Writes the table as JSON, in the layout of the options.
+    ///
+    /// With schema, the result is an object marked with `"format":"file-minidb"`,
+    /// with the `columns` as an array of objects with their `name`, `type`
+    /// (`str` or `int`) and `key` flag, and the `entries` in the layout.
+    pub fn to_json(&self, options: &JsonOptions) -> String {
+        let mut entries = String::new();
+        match options.layout {
+            JsonLayout::Rows => {
+                entries.push('[');
+                for (i, entry) in self.entries.iter().enumerate() {
+                    if i > 0 {
+                        entries.push(',');
+                    }
+                    entries.push('{');
+                    for (j, (column, value)) in entry.values.iter().enumerate() {
+                        if j > 0 {
+                            entries.push(',');
+                        }
+                        write_string(&mut entries, &column.name);
+                        entries.push(':');
+                        write_value(&mut entries, value);
+                    }
+                    entries.push('}');
+                }
+                entries.push(']');
+            }
+            JsonLayout::Columns => {
+                entries.push('{');
+                for (j, column) in self.columns.iter().enumerate() {
+                    if j > 0 {
+                        entries.push(',');
+                    }
+                    write_string(&mut entries, &column.name);
+                    entries.push_str(":[");
+                    for (i, entry) in self.entries.iter().enumerate() {
+                        if i > 0 {
+                            entries.push(',');
+                        }
+                        write_value(&mut entries, &entry.values[j].1);
+                    }
+                    entries.push(']');
+                }
+                entries.push('}');
+            }
+        }
+
+        if !options.schema {
+            return entries;
+        }
+
+        let mut result = format!("{{\"format\":\"{}\",\"columns\":[", FORMAT);
+        for (j, column) in self.columns.iter().enumerate() {
+            if j > 0 {
+                result.push(',');
+            }
+            result.push_str("{\"name\":");
+            write_string(&mut result, &column.name);
+            let _ = write!(
+                result,
+                ",\"type\":\"{}\",\"key\":{}}}",
+                type_name(&column.column_type),
+                column.is_key
+            );
+        }
+        result.push_str("],\"entries\":");
+        result.push_str(&entries);
+        result.push('}');
+        result
+    }
+
+    /// Reads a table with the columns from JSON in either layout, with or
+    /// without schema. A schema must match the columns.
+    ///
+    /// Every entry must have a value of the column type or null for every
+    /// column and no other values. All entries that do not are returned in
+    /// `JsonError::Rows`.
+    pub fn from_json(str: &str, columns: &[Column]) -> Result<Table, JsonError> {
+        let json = Parser::new(str).parse_document()?;
+        if !has_schema(&json) {
+            return from_entries(&json, columns);
+        }
+
+        if parse_schema(&json)? != columns {
+            return Err("The schema does not match the columns".to_string().into());
+        }
+        let entries = json
+            .get("entries")
+            .ok_or_else(|| "Missing entries".to_string())?;
+        from_entries(entries, columns)
+    }
+
+    /// Reads a table from JSON written with schema, taking the columns from it.
+    pub fn from_json_with_schema(str: &str) -> Result<Table, JsonError> {
+        let json = Parser::new(str).parse_document()?;
+        if !has_schema(&json) {
+            return Err("Missing schema".to_string().into());
+        }
+        let columns = parse_schema(&json)?;
+        let entries = json
+            .get("entries")
+            .ok_or_else(|| "Missing entries".to_string())?;
+        from_entries(entries, &columns)
+    }
+}
+
+fn type_name(column_type: &ColumnType) -> &'static str {
+    match column_type {
+        ColumnType::String => "str",
+        ColumnType::Integer => "int",
+    }
+}
+
+fn write_value(result: &mut String, value: &Value) {
+    match value {
+        Value::String(s) => write_string(result, s),
+        Value::Integer(i) => result.push_str(&i.to_string()),
+        Value::Null(_) => result.push_str("null"),
+    }
+}
+
+fn write_string(result: &mut String, str: &str) {
+    result.push('"');
+    for c in str.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+}
+
+// Tables written with schema are marked, as the entries of a table in the
+// columns layout can also have `columns` and `entries`.
+fn has_schema(json: &Json) -> bool {
+    matches!(json.get("format"), Some(Json::String(format)) if format == FORMAT)
+}
+
+fn parse_schema(json: &Json) -> Result<Vec<Column>, JsonError> {
+    let invalid = || JsonError::Error("Invalid schema".to_string());
+    let columns = match json.get("columns") {
+        Some(Json::Array(columns)) => columns,
+        _ => return Err(invalid()),
+    };
+
+    let mut result = vec![];
+    for column in columns {
+        let name = match column.get("name") {
+            Some(Json::String(name)) => name.clone(),
+            _ => return Err(invalid()),
+        };
+        let column_type = match column.get("type") {
+            Some(Json::String(t)) if t == "str" => ColumnType::String,
+            Some(Json::String(t)) if t == "int" => ColumnType::Integer,
+            _ => return Err(invalid()),
+        };
+        let is_key = match column.get("key") {
+            Some(Json::Bool(is_key)) => *is_key,
+            None => false,
+            _ => return Err(invalid()),
+        };
+        result.push(Column {
+            is_key,
+            name,
+            column_type,
+        });
+    }
+    Ok(result)
+}
+
+fn from_entries(json: &Json, columns: &[Column]) -> Result<Table, JsonError> {
+    // The values of every entry by column, None for missing values.
+    let rows: Vec<Result<Vec<Option<&Json>>, String>> = match json {
+        Json::Array(objects) => objects
+            .iter()
+            .map(|object| match object {
+                Json::Object(fields) => {
+                    if let Some((name, _)) = fields
+                        .iter()
+                        .find(|(name, _)| !columns.iter().any(|c| c.name == *name))
+                    {
+                        return Err(format!("Unknown column: {}", name));
+                    }
+                    Ok(columns.iter().map(|c| object.get(&c.name)).collect())
+                }
+                _ => Err("Entry is not an object".to_string()),
+            })
+            .collect(),
+        Json::Object(fields) => {
+            let mut arrays = vec![];
+            for (name, values) in fields {
+                if !columns.iter().any(|c| c.name == *name) {
+                    return Err(format!("Unknown column: {}", name).into());
+                }
+                match values {
+                    Json::Array(values) => arrays.push(values),
+                    _ => return Err(format!("Column {} is not an array", name).into()),
+                }
+            }
+            let length = arrays.first().map(|a| a.len()).unwrap_or(0);
+            if arrays.iter().any(|a| a.len() != length) {
+                return Err("Columns have different lengths".to_string().into());
+            }
+            (0..length)
+                .map(|i| {
+                    Ok(columns
+                        .iter()
+                        .map(|c| json.get(&c.name).map(|values| values.index(i)))
+                        .collect())
+                })
+                .collect()
+        }
+        _ => {
+            return Err("Entries are neither an array nor an object"
+                .to_string()
+                .into())
+        }
+    };
+
+    let mut table = Table::new(columns.to_vec())?;
+    let has_keys = columns.iter().any(|c| c.is_key);
+    let mut keys = HashSet::new();
+    let mut entries = vec![];
+    let mut errors = vec![];
+    for (index, row) in rows.into_iter().enumerate() {
+        let entry = row.and_then(|values| {
+            let values = values
+                .into_iter()
+                .zip(columns)
+                .map(|(value, column)| to_value(value, column))
+                .collect::<Result<Vec<Value>, String>>()?;
+            // Checks the keys, also against the entries before.
+            let entry = table.create_entry(values.clone())?;
+            if has_keys && !keys.insert(entry.get_key_values()) {
+                return Err("Key already exists".to_string());
+            }
+            Ok(values)
+        });
+        match entry {
+            Ok(values) => entries.push(values),
+            Err(error) => errors.push(JsonRowError { index, error }),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(JsonError::Rows(errors));
+    }
+
+    table.insert_all(entries)?;
+    Ok(table)
+}
+
+fn to_value(json: Option<&Json>, column: &Column) -> Result<Value, String> {
+    match (json, &column.column_type) {
+        (None, _) => Err(format!("Missing column: {}", column.name)),
+        (Some(Json::Null), t) => Ok(Value::Null(t.clone())),
+        (Some(Json::String(s)), ColumnType::String) => Ok(Value::String(s.clone())),
+        (Some(Json::Number(n)), ColumnType::Integer) => n
+            .parse::<i32>()
+            .map(Value::Integer)
+            .map_err(|_| format!("Not an integer in column {}: {}", column.name, n)),
+        (Some(_), t) => Err(format!(
+            "Expected {} in column {}",
+            type_name(t),
+            column.name
+        )),
+    }
+}
+
+// A parsed JSON value. Numbers are kept as written, to be parsed by type.
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, name: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    // Only called on arrays of checked length.
+    fn index(&self, index: usize) -> &Json {
+        match self {
+            Json::Array(values) => &values[index],
+            _ => unreachable!(),
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(str: &'a str) -> Parser<'a> {
+        Parser {
+            chars: str.char_indices().peekable(),
+            depth: 0,
+        }
+    }
+
+    fn parse_document(&mut self) -> Result<Json, String> {
+        let json = self.parse_value()?;
+        self.skip_whitespace();
+        match self.chars.next() {
+            None => Ok(json),
+            Some((position, _)) => Err(format!("Unexpected data at {}", position)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some((_, ' ' | '\t' | '\n' | '\r')) = self.chars.peek() {
+            self.chars.next();
+        }
+    }
+
+    fn unexpected(&mut self) -> String {
+        match self.chars.peek() {
+            Some((position, c)) => format!("Unexpected {} at {}", c, position),
+            None => "Unexpected end of JSON".to_string(),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some((_, c)) if *c == expected => {
+                self.chars.next();
+                Ok(())
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // Consumes the character if it is next.
+    fn next_is(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if let Some((_, c)) = self.chars.peek() {
+            if *c == expected {
+                self.chars.next();
+                return true;
+            }
+        }
+        false
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.peek().map(|(_, c)| *c) {
+            Some('{') | Some('[') if self.depth == MAX_DEPTH => {
+                Err("JSON is nested too deeply".to_string())
+            }
+            Some('{') => {
+                self.depth += 1;
+                let result = self.parse_object();
+                self.depth -= 1;
+                result
+            }
+            Some('[') => {
+                self.depth += 1;
+                let result = self.parse_array();
+                self.depth -= 1;
+                result
+            }
+            Some('"') => self.parse_string().map(Json::String),
+            Some('-') | Some('0'..='9') => self.parse_number(),
+            Some('t') => self.parse_literal("true", Json::Bool(true)),
+            Some('f') => self.parse_literal("false", Json::Bool(false)),
+            Some('n') => self.parse_literal("null", Json::Null),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, json: Json) -> Result<Json, String> {
+        for expected in literal.chars() {
+            match self.chars.peek() {
+                Some((_, c)) if *c == expected => {
+                    self.chars.next();
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+        Ok(json)
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let mut number = String::new();
+        while let Some((_, c)) = self.chars.peek() {
+            match c {
+                '0'..='9' | '-' | '+' | '.' | 'e' | 'E' => {
+                    number.push(*c);
+                    self.chars.next();
+                }
+                _ => break,
+            }
+        }
+        if number.parse::<f64>().is_err() {
+            return Err(format!("Invalid number: {}", number));
+        }
+        Ok(Json::Number(number))
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            match self.chars.next().map(|(_, c)| c) {
+                Some('"') => return Ok(result),
+                Some('\\') => match self.chars.next().map(|(_, c)| c) {
+                    Some('"') => result.push('"'),
+                    Some('\\') => result.push('\\'),
+                    Some('/') => result.push('/'),
+                    Some('b') => result.push('\u{8}'),
+                    Some('f') => result.push('\u{c}'),
+                    Some('n') => result.push('\n'),
+                    Some('r') => result.push('\r'),
+                    Some('t') => result.push('\t'),
+                    Some('u') => result.push(self.parse_unicode_escape()?),
+                    _ => return Err("Invalid escape in string".to_string()),
+                },
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err("Control character in string".to_string())
+                }
+                Some(c) => result.push(c),
+                None => return Err("Unexpected end of JSON".to_string()),
+            }
+        }
+    }
+
+    // After `\u`, with a second escape for characters outside the BMP.
+    fn parse_unicode_escape(&mut self) -> Result<char, String> {
+        let first = self.parse_hex()?;
+        let code = if (0xd800..0xdc00).contains(&first) {
+            if !(self.next_char() == Some('\\') && self.next_char() == Some('u')) {
+                return Err("Unpaired surrogate in string".to_string());
+            }
+            let second = self.parse_hex()?;
+            if !(0xdc00..0xe000).contains(&second) {
+                return Err("Unpaired surrogate in string".to_string());
+            }
+            0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)
+        } else {
+            first
+        };
+        char::from_u32(code).ok_or_else(|| "Invalid unicode escape".to_string())
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        self.chars.next().map(|(_, c)| c)
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, String> {
+        let mut result = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next_char()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| "Invalid unicode escape".to_string())?;
+            result = result * 16 + digit;
+        }
+        Ok(result)
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut values = vec![];
+        if self.next_is(']') {
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.parse_value()?);
+            if self.next_is(']') {
+                return Ok(Json::Array(values));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields: Vec<(String, Json)> = vec![];
+        if self.next_is('}') {
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.parse_string()?;
+            if fields.iter().any(|(n, _)| *n == name) {
+                return Err(format!("Duplicate name in object: {}", name));
+            }
+            self.expect(':')?;
+            fields.push((name, self.parse_value()?));
+            if self.next_is('}') {
+                return Ok(Json::Object(fields));
+            }
+            self.expect(',')?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Json, JsonError, JsonLayout, JsonOptions, JsonRowError, Parser};
+    use crate::column::Column;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    fn create_test_table() -> Table {
+        let mut table = Table::new(vec![
+            Column::key("Name", ColumnType::String),
+            Column::new("Age", ColumnType::Integer),
+        ])
+        .unwrap();
+        table.insert(vec!["Peter \"P\"".into(), 15.into()]).unwrap();
+        table
+            .insert(vec!["Wendy\n😀".into(), Value::Null(ColumnType::Integer)])
+            .unwrap();
+        table
+    }
+
+    #[test]
+    fn to_json() {
+        let table = create_test_table();
+        assert_eq!(
+            table.to_json(&JsonOptions::new()),
+            r#"[{"Name":"Peter \"P\"","Age":15},{"Name":"Wendy\n😀","Age":null}]"#
+        );
+        assert_eq!(
+            table.to_json(&JsonOptions::new().layout(JsonLayout::Columns)),
+            r#"{"Name":["Peter \"P\"","Wendy\n😀"],"Age":[15,null]}"#
+        );
+        assert_eq!(
+            table.to_json(&JsonOptions::new().schema(true)),
+            r#"{"format":"file-minidb","columns":[{"name":"Name","type":"str","key":true},{"name":"Age","type":"int","key":false}],"entries":[{"Name":"Peter \"P\"","Age":15},{"Name":"Wendy\n😀","Age":null}]}"#
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let table = create_test_table();
+        for layout in [JsonLayout::Rows, JsonLayout::Columns] {
+            for schema in [false, true] {
+                let options = JsonOptions::new().layout(layout).schema(schema);
+                let json = table.to_json(&options);
+                assert_eq!(Table::from_json(&json, &table.columns), Ok(table.clone()));
+                if schema {
+                    assert_eq!(Table::from_json_with_schema(&json), Ok(table.clone()));
+                } else {
+                    assert!(Table::from_json_with_schema(&json).is_err());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn columns_named_like_the_schema() {
+        let mut table = Table::new(vec![
+            Column::new("columns", ColumnType::Integer),
+            Column::new("entries", ColumnType::Integer),
+        ])
+        .unwrap();
+        table.insert(vec![1.into(), 2.into()]).unwrap();
+
+        let options = JsonOptions::new().layout(JsonLayout::Columns);
+        let json = table.to_json(&options);
+        assert_eq!(json, r#"{"columns":[1],"entries":[2]}"#);
+        assert_eq!(Table::from_json(&json, &table.columns), Ok(table.clone()));
+        assert!(Table::from_json_with_schema(&json).is_err());
+
+        let json = table.to_json(&options.schema(true));
+        assert_eq!(Table::from_json_with_schema(&json), Ok(table));
+    }
+
+    #[test]
+    fn row_errors() {
+        let columns = create_test_table().columns;
+        let json = r#"[
+            {"Name": "Peter", "Age": 15},
+            {"Name": "Wendy", "Age": "12"},
+            {"Name": "John"},
+            {"Name": null, "Age": 1},
+            {"Name": "Tinker", "Age": 1, "Wings": 2},
+            {"Name": "Michael", "Age": 1.5},
+            [],
+            {"Name": "Nana", "Age": null}
+        ]"#;
+        let indices = match Table::from_json(json, &columns) {
+            Err(JsonError::Rows(rows)) => rows.iter().map(|r| r.index).collect::<Vec<_>>(),
+            result => panic!("{:?}", result),
+        };
+        assert_eq!(indices, vec![1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(
+            Table::from_json(r#"{"Name":["a","b"],"Age":[1,"x"]}"#, &columns),
+            Err(JsonError::Rows(vec![JsonRowError {
+                index: 1,
+                error: "Expected int in column Age".to_string()
+            }]))
+        );
+        assert!(Table::from_json(r#"{"Name":["a","b"],"Age":[1]}"#, &columns).is_err());
+        assert_eq!(
+            Table::from_json(
+                r#"[{"Name":"a","Age":1},{"Name":"b","Age":2},{"Name":"a","Age":3}]"#,
+                &columns
+            ),
+            Err(JsonError::Rows(vec![JsonRowError {
+                index: 2,
+                error: "Key already exists".to_string()
+            }]))
+        );
+        assert!(Table::from_json(r#"{"columns":[],"entries":[]}"#, &columns).is_err());
+        assert!(Table::from_json(
+            r#"{"format":"file-minidb","columns":[],"entries":[]}"#,
+            &columns
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Parser::new(
+                r#" { "a" : [ 1, -2.5e3, true, false, null ], "b": "\u00e9\ud83d\ude00\/" } "#
+            )
+            .parse_document(),
+            Ok(Json::Object(vec![
+                (
+                    "a".to_string(),
+                    Json::Array(vec![
+                        Json::Number("1".to_string()),
+                        Json::Number("-2.5e3".to_string()),
+                        Json::Bool(true),
+                        Json::Bool(false),
+                        Json::Null
+                    ])
+                ),
+                ("b".to_string(), Json::String("é😀/".to_string()))
+            ]))
+        );
+
+        for invalid in [
+            "",
+            "[",
+            "[1,]",
+            "{\"a\"}",
+            "{\"a\":1,\"a\":2}",
+            "\"\\x\"",
+            "\"\\ud83d\"",
+            "tru",
+            "1 2",
+            "--1",
+            "\"\n\"",
+        ] {
+            assert!(
+                Parser::new(invalid).parse_document().is_err(),
+                "{}",
+                invalid
+            );
+        }
+        assert!(Parser::new(&"[".repeat(100)).parse_document().is_err());
+    }
+}
@@ -1,7 +1,36 @@
+// Lets the code generated by the derive macro refer to this crate in its tests.
+extern crate self as file_minidb;
+
+pub mod binary;
 pub mod column;
+pub mod compression;
+pub mod csv;
+pub mod database;
 pub mod deserialization;
+pub mod encryption;
 pub mod entry;
+pub mod expression;
+mod file;
+pub mod filter;
+pub mod fingerprint;
+pub mod foreign_key;
+mod hash;
+pub mod json;
+pub mod locked_table;
+pub mod logged_table;
+#[cfg(feature = "mmap")]
+pub mod mapped_table;
+pub mod pipeline;
+pub mod query;
+pub mod reader;
+pub mod row;
+#[cfg(feature = "serde")]
+mod serde_impl;
 pub mod serializer;
 pub mod table;
+#[cfg(all(test, feature = "compression"))]
+mod test_data;
+pub mod typed_table;
 pub mod types;
 pub mod values;
+pub mod writer;
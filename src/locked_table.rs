@@ -0,0 +1,208 @@
+use crate::table::Table;
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+/// How a `LockedTable` is locked against other processes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LockMode {
+    /// Several handles can read the table at the same time.
+    Shared,
+    /// Only this handle can read and write the table.
+    Exclusive,
+}
+
+/// A table file opened with an advisory lock, held for the lifetime of the handle.
+///
+/// The lock is taken on a file next to the table file, named like the table
+/// file with `.lock` appended, as writing the table replaces the table file.
+/// Other processes are only kept out if they lock the table as well.
+#[derive(Debug)]
+pub struct LockedTable {
+    table: Table,
+    path: PathBuf,
+    mode: LockMode,
+    // Unlocked when closed on drop
+    _lock: File,
+}
+
+impl LockedTable {
+    /// Locks and loads the table file, waiting until the lock is available.
+    pub fn open(path: &Path, mode: LockMode) -> Result<LockedTable, String> {
+        let lock = open_lock_file(path)?;
+        match mode {
+            LockMode::Shared => lock.lock_shared(),
+            LockMode::Exclusive => lock.lock(),
+        }
+        .map_err(|e| e.to_string())?;
+
+        LockedTable::load(path, mode, lock)
+    }
+
+    /// Locks and loads the table file.
+    /// Will error immediately if the table is locked by another handle.
+    pub fn try_open(path: &Path, mode: LockMode) -> Result<LockedTable, String> {
+        let lock = open_lock_file(path)?;
+        match mode {
+            LockMode::Shared => lock.try_lock_shared(),
+            LockMode::Exclusive => lock.try_lock(),
+        }
+        .map_err(|e| match e {
+            TryLockError::WouldBlock => format!("Table is locked: {}", path.display()),
+            TryLockError::Error(e) => e.to_string(),
+        })?;
+
+        LockedTable::load(path, mode, lock)
+    }
+
+    fn load(path: &Path, mode: LockMode, lock: File) -> Result<LockedTable, String> {
+        Ok(LockedTable {
+            table: Table::from_file(path)?,
+            path: path.to_path_buf(),
+            mode,
+            _lock: lock,
+        })
+    }
+
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn table_mut(&mut self) -> &mut Table {
+        &mut self.table
+    }
+
+    pub fn get_mode(&self) -> LockMode {
+        self.mode
+    }
+
+    /// Writes the table back to its file, see `Table::write_file`.
+    /// Will error if the table is only locked shared.
+    pub fn save(&self) -> Result<(), String> {
+        if self.mode != LockMode::Exclusive {
+            return Err(format!(
+                "Table is not locked exclusively: {}",
+                self.path.display()
+            ));
+        }
+
+        self.table.write_file(&self.path)
+    }
+}
+
+// The lock file is never removed, as removing it while another process waits
+// for its lock would let a third process lock a new file.
+fn open_lock_file(path: &Path) -> Result<File, String> {
+    let mut lock_path = path.as_os_str().to_os_string();
+    lock_path.push(".lock");
+
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{LockMode, LockedTable};
+    use crate::column::Column;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    // Set for the test binary started as a child process by `child_open`.
+    const CHILD_PATH: &str = "FILE_MINIDB_LOCK_CHILD_PATH";
+    const CHILD_MODE: &str = "FILE_MINIDB_LOCK_CHILD_MODE";
+
+    fn test_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "file-minidb-locked-{}-{}.table",
+            name,
+            std::process::id()
+        ));
+        let mut table = Table::new(vec![Column::key("Name", ColumnType::String)]).unwrap();
+        table.insert(vec!["Peter".into()]).unwrap();
+        table.write_file(&path).unwrap();
+        path
+    }
+
+    fn remove_files(path: &Path) {
+        fs::remove_file(path).unwrap();
+        fs::remove_file(path.with_extension("table.lock")).unwrap();
+    }
+
+    // Runs `child` in a new process and returns if it could open the table.
+    fn child_open(path: &Path, mode: &str) -> bool {
+        Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "locked_table::test::child", "--test-threads=1"])
+            .env(CHILD_PATH, path)
+            .env(CHILD_MODE, mode)
+            .output()
+            .unwrap()
+            .status
+            .success()
+    }
+
+    #[test]
+    fn child() {
+        let path = match std::env::var_os(CHILD_PATH) {
+            Some(path) => PathBuf::from(path),
+            // Not started by `child_open`
+            None => return,
+        };
+        let mode = match std::env::var(CHILD_MODE).unwrap().as_str() {
+            "shared" => LockMode::Shared,
+            _ => LockMode::Exclusive,
+        };
+
+        LockedTable::try_open(&path, mode).unwrap();
+    }
+
+    #[test]
+    fn exclusive_between_processes() {
+        let path = test_path("exclusive");
+
+        let locked = LockedTable::try_open(&path, LockMode::Exclusive).unwrap();
+        assert!(!child_open(&path, "shared"));
+        assert!(!child_open(&path, "exclusive"));
+
+        drop(locked);
+        assert!(child_open(&path, "exclusive"));
+
+        remove_files(&path);
+    }
+
+    #[test]
+    fn shared_between_processes() {
+        let path = test_path("shared");
+
+        let locked = LockedTable::try_open(&path, LockMode::Shared).unwrap();
+        assert!(child_open(&path, "shared"));
+        assert!(!child_open(&path, "exclusive"));
+        assert!(locked.save().is_err());
+
+        remove_files(&path);
+    }
+
+    #[test]
+    fn save_exclusive() {
+        let path = test_path("save");
+
+        let mut locked = LockedTable::open(&path, LockMode::Exclusive).unwrap();
+        locked.table_mut().insert(vec!["Wendy".into()]).unwrap();
+        locked.save().unwrap();
+        drop(locked);
+
+        let reopened = LockedTable::open(&path, LockMode::Shared).unwrap();
+        assert_eq!(reopened.table().get_entries().len(), 2);
+
+        remove_files(&path);
+    }
+}
@@ -0,0 +1,458 @@
+use crate::column::Column;
+use crate::entry::Entry;
+use crate::file;
+use crate::hash::fnv1a;
+use crate::serializer::Serializable;
+use crate::table::Table;
+use crate::values::Value;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const SNAPSHOT_PREFIX: &str = "snapshot,";
+const CHECKPOINT_PREFIX: &str = "checkpoint,";
+
+/// A table persisted incrementally.
+///
+/// The table is stored as a snapshot in the format of `Table::write_file` and a
+/// log file next to it, named like the snapshot with `.log` appended. Every
+/// change appends a record to the log and syncs it before it is acknowledged.
+/// Opening replays the log on top of the snapshot, `checkpoint` writes a new
+/// snapshot and empties the log.
+///
+/// The log starts with a hash of the snapshot it belongs to, so that a log
+/// already contained in a newer snapshot is never replayed twice. Before a
+/// checkpoint writes the new snapshot, the hash of the new snapshot is
+/// appended to the log. A log for another snapshot is only dropped if it ends
+/// with the hash of the current one. If the snapshot was written outside of
+/// `LoggedTable`, opening fails and the log is kept.
+#[derive(Debug)]
+pub struct LoggedTable {
+    table: Table,
+    path: PathBuf,
+    log: File,
+}
+
+impl LoggedTable {
+    /// Stores the table as a new snapshot with an empty log.
+    /// Will replace an existing snapshot and log at the path.
+    pub fn create(path: &Path, table: Table) -> Result<LoggedTable, String> {
+        // Removed first, so that a crash before the new log is written
+        // doesn't leave the old log next to the new snapshot.
+        match fs::remove_file(log_path(path)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+            _ => {}
+        }
+        let log = write_snapshot(path, &table.serialize_current())?;
+
+        Ok(LoggedTable {
+            table,
+            path: path.to_path_buf(),
+            log,
+        })
+    }
+
+    /// Loads the snapshot at the path and replays the log on top of it.
+    /// A record at the end of the log, which was only partially written, was
+    /// never acknowledged and is dropped.
+    /// Will error if the log has records for another snapshot, which happens
+    /// if the snapshot was written outside of `LoggedTable`.
+    pub fn open(path: &Path) -> Result<LoggedTable, String> {
+        let snapshot = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut table = Table::deserialize(snapshot.clone())?;
+
+        let log_path = log_path(path);
+        let log_content = match fs::read_to_string(&log_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.to_string()),
+        };
+
+        // Without a log for this snapshot, the log was lost or already
+        // checkpointed into the snapshot.
+        let snapshot_hash = fnv1a(snapshot.as_bytes());
+        let header = header(snapshot_hash);
+        if !log_content.starts_with(&header) {
+            if !is_checkpointed(&log_content, snapshot_hash) {
+                return Err(format!(
+                    "Log {} does not belong to the snapshot",
+                    log_path.display()
+                ));
+            }
+            let log = create_log(path, snapshot_hash)?;
+            return Ok(LoggedTable {
+                table,
+                path: path.to_path_buf(),
+                log,
+            });
+        }
+
+        let mut valid_length = header.len();
+        for record in log_content[header.len()..].split_inclusive('\n') {
+            if !record.ends_with('\n') {
+                break;
+            }
+            replay(&mut table, record.trim_end_matches('\n'))?;
+            valid_length += record.len();
+        }
+
+        let log = OpenOptions::new()
+            .append(true)
+            .open(&log_path)
+            .map_err(|e| e.to_string())?;
+        if valid_length < log_content.len() {
+            log.set_len(valid_length as u64)
+                .and_then(|_| log.sync_all())
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(LoggedTable {
+            table,
+            path: path.to_path_buf(),
+            log,
+        })
+    }
+
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    /// Inserts into the table, see `Table::insert`.
+    /// Returns once the change is durable in the log.
+    pub fn insert(&mut self, entry: Vec<Value>) -> Result<(), String> {
+        self.table.insert(entry)?;
+
+        let record = format!("insert,{}", self.table.entries.last().unwrap().serialize());
+        if let Err(e) = self.append(&record) {
+            self.table.entries.pop();
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Removes from the table, see `Table::remove`.
+    /// Returns once the change is durable in the log.
+    pub fn remove(&mut self, keys: Vec<Value>) -> Result<bool, String> {
+        let before = self.table.entries.clone();
+        if !self.table.remove(keys.clone()) {
+            return Ok(false);
+        }
+
+        let record = format!(
+            "remove,{}",
+            serialize_values(&key_columns(&self.table), keys)
+        );
+        if let Err(e) = self.append(&record) {
+            self.table.entries = before;
+            return Err(e);
+        }
+
+        Ok(true)
+    }
+
+    /// Updates an entry of the table, see `Table::update`.
+    /// Returns once the change is durable in the log.
+    pub fn update(&mut self, keys: Vec<Value>, entry: Vec<Value>) -> Result<bool, String> {
+        let before = self.table.entries.clone();
+        if !self.table.update(keys.clone(), entry.clone())? {
+            return Ok(false);
+        }
+
+        let record = format!(
+            "update,{}",
+            serialize_values(
+                &update_columns(&self.table),
+                keys.into_iter().chain(entry).collect()
+            )
+        );
+        if let Err(e) = self.append(&record) {
+            self.table.entries = before;
+            return Err(e);
+        }
+
+        Ok(true)
+    }
+
+    /// Writes the table as the new snapshot and empties the log.
+    pub fn checkpoint(&mut self) -> Result<(), String> {
+        let snapshot = self.table.serialize_current();
+        self.append(&checkpoint_record(fnv1a(snapshot.as_bytes())))?;
+        self.log = write_snapshot(&self.path, &snapshot)?;
+        Ok(())
+    }
+
+    fn append(&mut self, record: &str) -> Result<(), String> {
+        self.log
+            .write_all(format!("{}\n", record).as_bytes())
+            .and_then(|_| self.log.sync_data())
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn log_path(path: &Path) -> PathBuf {
+    let mut log = path.as_os_str().to_os_string();
+    log.push(".log");
+    PathBuf::from(log)
+}
+
+fn header(snapshot_hash: u64) -> String {
+    format!("{}{:016x}\n", SNAPSHOT_PREFIX, snapshot_hash)
+}
+
+fn checkpoint_record(snapshot_hash: u64) -> String {
+    format!("{}{:016x}", CHECKPOINT_PREFIX, snapshot_hash)
+}
+
+// A log for another snapshot is only contained in the snapshot if its last
+// record is the checkpoint to the snapshot. A log without records has nothing
+// to lose.
+fn is_checkpointed(log_content: &str, snapshot_hash: u64) -> bool {
+    let last = log_content
+        .split_inclusive('\n')
+        .skip(1)
+        .filter(|r| r.ends_with('\n'))
+        .last();
+    match last {
+        Some(record) => record.trim_end_matches('\n') == checkpoint_record(snapshot_hash),
+        None => true,
+    }
+}
+
+// The snapshot is written before the log, a crash in between leaves a log with
+// the hash of the old snapshot, which ends with the checkpoint to the new one.
+fn write_snapshot(path: &Path, snapshot: &str) -> Result<File, String> {
+    file::write_atomic(path, snapshot.as_bytes())?;
+    create_log(path, fnv1a(snapshot.as_bytes()))
+}
+
+fn create_log(path: &Path, snapshot_hash: u64) -> Result<File, String> {
+    let log_path = log_path(path);
+    file::write_atomic(&log_path, header(snapshot_hash).as_bytes())?;
+
+    OpenOptions::new()
+        .append(true)
+        .open(&log_path)
+        .map_err(|e| e.to_string())
+}
+
+fn key_columns(table: &Table) -> Vec<Column> {
+    table.columns.iter().filter(|c| c.is_key).cloned().collect()
+}
+
+// An update record holds the keys followed by the new entry.
+fn update_columns(table: &Table) -> Vec<Column> {
+    key_columns(table)
+        .into_iter()
+        .chain(table.columns.iter().cloned())
+        .collect()
+}
+
+fn serialize_values(columns: &[Column], values: Vec<Value>) -> String {
+    Entry::new(columns.iter().cloned().zip(values).collect()).serialize()
+}
+
+fn deserialize_values(str: &str, columns: Vec<Column>) -> Result<Vec<Value>, String> {
+    if columns.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(Entry::deserialize_data(str.to_string(), columns)?.get_values())
+}
+
+fn replay(table: &mut Table, record: &str) -> Result<(), String> {
+    let invalid = || format!("Invalid log record: {}", record);
+
+    let (operation, data) = record.split_once(',').ok_or_else(invalid)?;
+    match operation {
+        "insert" => table.insert(deserialize_values(data, table.columns.clone())?),
+        "remove" => {
+            let keys = deserialize_values(data, key_columns(table))?;
+            if table.remove(keys) {
+                Ok(())
+            } else {
+                Err(invalid())
+            }
+        }
+        "update" => {
+            let mut values = deserialize_values(data, update_columns(table))?;
+            let entry = values.split_off(values.len() - table.columns.len());
+            if table.update(values, entry)? {
+                Ok(())
+            } else {
+                Err(invalid())
+            }
+        }
+        // Only read when the log is for another snapshot.
+        "checkpoint" => Ok(()),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{checkpoint_record, log_path, LoggedTable};
+    use crate::column::Column;
+    use crate::file;
+    use crate::hash::fnv1a;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    fn test_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "file-minidb-logged-{}-{}.table",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(log_path(&path));
+        path
+    }
+
+    fn remove_files(path: &Path) {
+        fs::remove_file(path).unwrap();
+        fs::remove_file(log_path(path)).unwrap();
+    }
+
+    fn create_test_table(path: &Path) -> LoggedTable {
+        let table = Table::new(vec![
+            Column::key("Name", ColumnType::String),
+            Column::new("Age", ColumnType::Integer),
+        ])
+        .unwrap();
+        let mut logged = LoggedTable::create(path, table).unwrap();
+
+        logged.insert(vec!["Peter".into(), 15.into()]).unwrap();
+        logged
+            .insert(vec!["Wendy, Darling".into(), 12.into()])
+            .unwrap();
+        logged.insert(vec!["John".into(), 10.into()]).unwrap();
+        logged.remove(vec!["John".into()]).unwrap();
+        logged
+            .update(
+                vec!["Peter".into()],
+                vec!["Peter Pan".into(), Value::Null(ColumnType::Integer)],
+            )
+            .unwrap();
+
+        logged
+    }
+
+    #[test]
+    fn replay_after_crash() {
+        let path = test_path("replay");
+        let logged = create_test_table(&path);
+        let expected = logged.table().clone();
+        // Crash without checkpoint
+        drop(logged);
+
+        let opened = LoggedTable::open(&path).unwrap();
+        assert_eq!(opened.table(), &expected);
+        assert_eq!(Table::from_file(&path).unwrap().get_entries().len(), 0);
+
+        remove_files(&path);
+    }
+
+    #[test]
+    fn partial_record() {
+        let path = test_path("partial");
+        let logged = create_test_table(&path);
+        let expected = logged.table().clone();
+        drop(logged);
+
+        // Crash while appending a record
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(log_path(&path))
+            .unwrap();
+        write!(log, "insert,\"Tinker").unwrap();
+
+        let mut opened = LoggedTable::open(&path).unwrap();
+        assert_eq!(opened.table(), &expected);
+
+        opened.insert(vec!["Tinker".into(), 1.into()]).unwrap();
+        drop(opened);
+        assert_eq!(
+            LoggedTable::open(&path)
+                .unwrap()
+                .table()
+                .get_entries()
+                .len(),
+            3
+        );
+
+        remove_files(&path);
+    }
+
+    #[test]
+    fn checkpoint() {
+        let path = test_path("checkpoint");
+        let mut logged = create_test_table(&path);
+        logged.checkpoint().unwrap();
+
+        assert_eq!(&Table::from_file(&path).unwrap(), logged.table());
+        assert_eq!(
+            fs::read_to_string(log_path(&path)).unwrap().lines().count(),
+            1
+        );
+
+        logged.remove(vec!["Peter Pan".into()]).unwrap();
+        let expected = logged.table().clone();
+        drop(logged);
+        assert_eq!(LoggedTable::open(&path).unwrap().table(), &expected);
+
+        remove_files(&path);
+    }
+
+    #[test]
+    fn crash_during_checkpoint() {
+        let path = test_path("crash_checkpoint");
+        let logged = create_test_table(&path);
+        let expected = logged.table().clone();
+
+        // Crash after the snapshot is written, but before the log is emptied
+        let snapshot = expected.serialize_current();
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(log_path(&path))
+            .unwrap();
+        writeln!(log, "{}", checkpoint_record(fnv1a(snapshot.as_bytes()))).unwrap();
+        file::write_atomic(&path, snapshot.as_bytes()).unwrap();
+        drop(logged);
+
+        assert_eq!(LoggedTable::open(&path).unwrap().table(), &expected);
+
+        remove_files(&path);
+    }
+
+    #[test]
+    fn snapshot_written_outside() {
+        let path = test_path("outside");
+        let logged = create_test_table(&path);
+        let expected = logged.table().clone();
+        drop(logged);
+
+        let log = fs::read_to_string(log_path(&path)).unwrap();
+        let mut other = Table::new(expected.columns.clone()).unwrap();
+        other.insert(vec!["Tinker".into(), 1.into()]).unwrap();
+        other.write_file(&path).unwrap();
+
+        assert!(LoggedTable::open(&path).is_err());
+        assert_eq!(fs::read_to_string(log_path(&path)).unwrap(), log);
+
+        // The old snapshot is still replayed with the log
+        Table::new(expected.columns.clone())
+            .unwrap()
+            .write_file(&path)
+            .unwrap();
+        assert_eq!(LoggedTable::open(&path).unwrap().table(), &expected);
+
+        remove_files(&path);
+    }
+}
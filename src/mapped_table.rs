@@ -0,0 +1,448 @@
+use crate::binary;
+use crate::column::Column;
+use crate::compression;
+use crate::deserialization::Header;
+use crate::encryption;
+use crate::entry::Entry;
+use crate::file;
+use crate::serializer::{Serializable, HEADER_PREFIX};
+use crate::table::Table;
+use crate::values::Value;
+
+use memmap2::Mmap;
+
+use std::collections::HashMap;
+use std::fs::{self, File, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// Starts the first line of an index file.
+const INDEX_PREFIX: &str = "#file-minidb-index";
+
+/// A read-only table file in the text format, mapped into memory, which only
+/// decodes the entries that are accessed.
+///
+/// On open, the position of every row and the keys of every entry are read
+/// from an index file next to the table file, named like the table file with
+/// `.index` appended. If the index is missing or the size or modification time
+/// of the table file changed since it was built, it is built from the table
+/// file and written again.
+///
+/// The table file must not be changed while it is mapped, `LockedTable` can be
+/// used to keep other processes from writing it.
+#[derive(Debug)]
+pub struct MappedTable {
+    map: Mmap,
+    index_path: PathBuf,
+    header: Header,
+    columns: Vec<Column>,
+    // The position of the first byte of every row.
+    rows: Vec<usize>,
+    // The row of every entry by its keys, empty for tables without keys.
+    keys: HashMap<Vec<Value>, usize>,
+}
+
+impl MappedTable {
+    /// Maps the table file and loads or builds its index.
+    pub fn open(path: &Path) -> Result<MappedTable, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let index_header = index_header(&file.metadata().map_err(|e| e.to_string())?);
+        // Safety: the map is only read, and the documentation requires the
+        // file not to be changed while it is mapped.
+        let map = unsafe { Mmap::map(&file) }.map_err(|e| e.to_string())?;
+        if binary::is_binary(&map)
+            || compression::is_compressed(&map)
+            || encryption::is_encrypted(&map)
+        {
+            return Err("Only tables in the text format can be mapped".to_string());
+        }
+
+        let mut lines = Lines {
+            map: &map,
+            position: 0,
+        };
+        let mut line = lines.next().ok_or_else(|| "String is empty".to_string())?;
+        let header = if line.starts_with(HEADER_PREFIX.as_bytes()) {
+            let header = Header::deserialize_line(&decode(line)?)?;
+            line = lines.next().ok_or_else(|| "String is empty".to_string())?;
+            header
+        } else {
+            Header::NONE
+        };
+        let (columns, _) = Column::deserialize_columns(decode(line)?)?;
+        Table::new(columns.clone())?;
+        let rows_start = lines.position;
+
+        let mut table = MappedTable {
+            map,
+            index_path: index_path(path),
+            header,
+            columns,
+            rows: vec![],
+            keys: HashMap::new(),
+        };
+
+        let index_path = table.index_path.clone();
+        let is_loaded = match &index_header {
+            Some(index_header) => table.load_index(&index_path, index_header),
+            None => false,
+        };
+        if !is_loaded {
+            table.build_index(rows_start)?;
+            // The index only saves time, so the table can be used without it,
+            // e.g. if the directory is read-only.
+            if let Some(index_header) = &index_header {
+                let index = table.serialize_index(index_header);
+                let _ = file::write_atomic(&index_path, index.as_bytes());
+            }
+        }
+
+        if let Some(expected) = table.header.entries {
+            if expected != table.rows.len() {
+                return Err(format!(
+                    "Expected {} entries, found {}",
+                    expected,
+                    table.rows.len()
+                ));
+            }
+        }
+
+        Ok(table)
+    }
+
+    pub fn get_columns(&self) -> &Vec<Column> {
+        &self.columns
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Decodes the entry at the position in the file, starting at 0.
+    pub fn get_entry(&self, index: usize) -> Result<Option<Entry>, String> {
+        match self.rows.get(index) {
+            Some(start) => self.decode_row(*start).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Decodes the entry with the key values, in the order of the columns.
+    /// Will error for tables without keys, and if the entry found through the
+    /// index has other keys, which happens if the table file was changed
+    /// without changing its size or modification time. The index is removed
+    /// then, so that it is built again on the next open.
+    pub fn get(&self, keys: &[Value]) -> Result<Option<Entry>, String> {
+        if !self.columns.iter().any(|c| c.is_key) {
+            return Err("Table has no keys".to_string());
+        }
+        let entry = match self.keys.get(keys) {
+            Some(index) => self.get_entry(*index)?,
+            None => return Ok(None),
+        };
+        match entry {
+            Some(entry) if entry.get_key_values() == keys => Ok(Some(entry)),
+            _ => {
+                let _ = fs::remove_file(&self.index_path);
+                Err("Index does not match the table file".to_string())
+            }
+        }
+    }
+
+    /// Decodes all entries into a table.
+    pub fn to_table(&self) -> Result<Table, String> {
+        let mut table = Table::new(self.columns.clone())?;
+        let mut entries = Vec::with_capacity(self.rows.len());
+        for start in &self.rows {
+            entries.push(self.decode_row(*start)?.get_values());
+        }
+        table.insert_all(entries)?;
+        Ok(table)
+    }
+
+    fn decode_row(&self, start: usize) -> Result<Entry, String> {
+        let line = Lines {
+            map: &self.map,
+            position: start,
+        }
+        .next()
+        .ok_or_else(|| "Row out of range".to_string())?;
+        let line = decode(line)?;
+        let data = self.header.verify_checksum(&line)?;
+        Entry::deserialize_data(data.to_string(), self.columns.clone())
+    }
+
+    fn key_columns(&self) -> Vec<Column> {
+        self.columns.iter().filter(|c| c.is_key).cloned().collect()
+    }
+
+    fn build_index(&mut self, rows_start: usize) -> Result<(), String> {
+        let has_keys = self.columns.iter().any(|c| c.is_key);
+        let mut lines = Lines {
+            map: &self.map,
+            position: rows_start,
+        };
+        let mut rows = vec![];
+        let mut keys = HashMap::new();
+
+        loop {
+            let start = lines.position;
+            match lines.next() {
+                Some(line) if !line.is_empty() => {}
+                _ => break,
+            }
+            if has_keys {
+                let entry = self
+                    .decode_row(start)
+                    .map_err(|e| format!("Corrupt row {}: {}", rows.len() + 1, e))?;
+                if keys.insert(entry.get_key_values(), rows.len()).is_some() {
+                    return Err("Key already exists".to_string());
+                }
+            }
+            rows.push(start);
+        }
+
+        self.rows = rows;
+        self.keys = keys;
+        Ok(())
+    }
+
+    // The index has the header line, see `index_header`, followed by a line
+    // for every row with its position and its keys.
+    fn serialize_index(&self, index_header: &str) -> String {
+        let mut keys = vec![None; self.rows.len()];
+        for (values, index) in &self.keys {
+            keys[*index] = Some(values);
+        }
+
+        let mut result = format!("{}\n", index_header);
+        for (start, keys) in self.rows.iter().zip(keys) {
+            result.push_str(&start.to_string());
+            if let Some(keys) = keys {
+                for value in keys {
+                    result.push(',');
+                    result.push_str(&value.serialize());
+                }
+            }
+            result.push('\n');
+        }
+        result
+    }
+
+    // Returns false if there is no index with the header line.
+    fn load_index(&mut self, path: &Path, index_header: &str) -> bool {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return false,
+        };
+        let mut lines = content.lines();
+        if lines.next() != Some(index_header) {
+            return false;
+        }
+
+        let key_columns = self.key_columns();
+        let mut rows = vec![];
+        let mut keys = HashMap::new();
+        for line in lines {
+            let (start, values) = line.split_once(',').unwrap_or((line, ""));
+            match start.parse::<usize>() {
+                Ok(start) if start < self.map.len() => rows.push(start),
+                _ => return false,
+            }
+            if !key_columns.is_empty() {
+                match Entry::deserialize_data(values.to_string(), key_columns.clone()) {
+                    Ok(entry) => keys.insert(entry.get_values(), rows.len() - 1),
+                    Err(_) => return false,
+                };
+            }
+        }
+
+        self.rows = rows;
+        self.keys = keys;
+        true
+    }
+}
+
+// The first line of the index of a table file, with the size and modification
+// time of the file, so that a stale index is found without reading the file.
+// None if the file system has no modification times.
+fn index_header(metadata: &Metadata) -> Option<String> {
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!(
+        "{} size={} modified={}",
+        INDEX_PREFIX,
+        metadata.len(),
+        modified.as_nanos()
+    ))
+}
+
+fn index_path(path: &Path) -> PathBuf {
+    let mut index = path.as_os_str().to_os_string();
+    index.push(".index");
+    PathBuf::from(index)
+}
+
+fn decode(line: &[u8]) -> Result<String, String> {
+    String::from_utf8(line.to_vec()).map_err(|e| e.to_string())
+}
+
+// The lines of the mapped file, without their line break.
+struct Lines<'a> {
+    map: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if self.position >= self.map.len() {
+            return None;
+        }
+        let rest = &self.map[self.position..];
+        let end = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+        self.position += (end + 1).min(rest.len());
+        Some(&rest[..end])
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{index_path, MappedTable};
+    use crate::column::Column;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+
+    fn test_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "file-minidb-mapped-{}-{}.table",
+            name,
+            std::process::id()
+        ))
+    }
+
+    fn create_test_table() -> Table {
+        let mut table = Table::new(vec![
+            Column::key("Name", ColumnType::String),
+            Column::key("Number", ColumnType::Integer),
+            Column::new("Age", ColumnType::Integer),
+        ])
+        .unwrap();
+        table
+            .insert(vec!["Peter".into(), 1.into(), 15.into()])
+            .unwrap();
+        table
+            .insert(vec!["Wendy, Moira".into(), 2.into(), 12.into()])
+            .unwrap();
+        table
+            .insert(vec![
+                "John".into(),
+                3.into(),
+                Value::Null(ColumnType::Integer),
+            ])
+            .unwrap();
+        table
+    }
+
+    #[test]
+    fn lookup() {
+        let path = test_path("lookup");
+        let table = create_test_table();
+        table.write_file(&path).unwrap();
+
+        // Once building the index, once loading it.
+        for _ in 0..2 {
+            let mapped = MappedTable::open(&path).unwrap();
+            let index = fs::read_to_string(index_path(&path)).unwrap();
+            let size = fs::metadata(&path).unwrap().len();
+            assert!(index.starts_with(&format!("#file-minidb-index size={} modified=", size)));
+            assert_eq!(mapped.len(), 3);
+            assert_eq!(
+                mapped.get(&["Wendy, Moira".into(), 2.into()]),
+                Ok(Some(table.get_entries()[1].clone()))
+            );
+            assert_eq!(mapped.get(&["Wendy, Moira".into(), 3.into()]), Ok(None));
+            assert_eq!(
+                mapped.get_entry(2),
+                Ok(Some(table.get_entries()[2].clone()))
+            );
+            assert_eq!(mapped.get_entry(3), Ok(None));
+            assert_eq!(mapped.to_table(), Ok(table.clone()));
+        }
+
+        // A stale index is rebuilt.
+        let mut changed = create_test_table();
+        changed.remove(vec!["Peter".into(), 1.into()]);
+        changed.write_file(&path).unwrap();
+        let mapped = MappedTable::open(&path).unwrap();
+        assert_eq!(mapped.len(), 2);
+        assert_eq!(mapped.get(&["Peter".into(), 1.into()]), Ok(None));
+        assert!(mapped.get(&["John".into(), 3.into()]).unwrap().is_some());
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(index_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn stale_index_with_same_size_and_time() {
+        let path = test_path("stale");
+        create_test_table().write_file(&path).unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        MappedTable::open(&path).unwrap();
+
+        let size = fs::metadata(&path).unwrap().len();
+        let mut changed = create_test_table();
+        changed.remove(vec!["Peter".into(), 1.into()]);
+        changed
+            .insert(vec!["Petra".into(), 1.into(), 15.into()])
+            .unwrap();
+        changed.write_file(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), size);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let mapped = MappedTable::open(&path).unwrap();
+        assert!(mapped.get(&["Peter".into(), 1.into()]).is_err());
+        assert!(!index_path(&path).exists());
+        let mapped = MappedTable::open(&path).unwrap();
+        assert_eq!(mapped.get(&["Peter".into(), 1.into()]), Ok(None));
+        assert!(mapped.get(&["Petra".into(), 1.into()]).unwrap().is_some());
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(index_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn corrupt_row_is_only_found_when_decoded() {
+        let path = test_path("corrupt");
+        let mut table = Table::new(vec![Column::new("Name", ColumnType::String)]).unwrap();
+        table.insert(vec!["Peter".into()]).unwrap();
+        table.insert(vec!["Wendy".into()]).unwrap();
+        table.write_file(&path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replace("Wendy", "Wendi")).unwrap();
+
+        let mapped = MappedTable::open(&path).unwrap();
+        assert!(mapped.get(&["Peter".into()]).is_err());
+        assert!(mapped.get_entry(0).unwrap().is_some());
+        assert!(mapped.get_entry(1).is_err());
+
+        table.write_binary_file(&path).unwrap();
+        assert!(MappedTable::open(&path).is_err());
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(index_path(&path)).unwrap();
+    }
+}
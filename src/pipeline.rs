@@ -0,0 +1,499 @@
+use crate::column::Column;
+use crate::entry::Entry;
+use crate::expression::{Expression, Operator};
+use crate::table::Table;
+use crate::values::Value;
+
+use std::borrow::Cow;
+
+/// The steps of a `Pipeline`. Each step consumes the rows of its input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Plan {
+    /// Reads all entries of the table.
+    Scan,
+    /// Reads the single entry with the given key values, in the order of the key columns.
+    KeyLookup {
+        keys: Vec<Value>,
+    },
+    Filter {
+        input: Box<Plan>,
+        predicate: Expression,
+    },
+    /// Keeps the named columns, none of them is a key column afterwards.
+    Project {
+        input: Box<Plan>,
+        columns: Vec<String>,
+    },
+    Sort {
+        input: Box<Plan>,
+        by: Expression,
+        descending: bool,
+    },
+    Limit {
+        input: Box<Plan>,
+        count: usize,
+    },
+}
+
+/// A lazily executed query over a table.
+/// Nothing is read from the table until `execute` is called, rows are only
+/// cloned where a step needs to modify or collect them.
+#[derive(Clone, Debug)]
+pub struct Pipeline<'a> {
+    table: &'a Table,
+    plan: Plan,
+}
+
+type Rows<'a> = Box<dyn Iterator<Item = Result<Cow<'a, Entry>, String>> + 'a>;
+
+impl<'a> Pipeline<'a> {
+    /// Starts a pipeline reading all entries of the table.
+    pub fn scan(table: &'a Table) -> Pipeline<'a> {
+        Pipeline {
+            table,
+            plan: Plan::Scan,
+        }
+    }
+
+    /// Keeps the rows the predicate is true for.
+    /// A filter directly on the table that fixes all key columns to constants
+    /// is executed as a key lookup.
+    pub fn filter(self, predicate: Expression) -> Pipeline<'a> {
+        let plan = self.push_filter(self.plan.clone(), predicate);
+        Pipeline { plan, ..self }
+    }
+
+    /// Keeps the columns with the given names, in the given order.
+    pub fn project(self, columns: &[&str]) -> Pipeline<'a> {
+        let plan = Plan::Project {
+            input: Box::new(self.plan),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+        };
+        Pipeline { plan, ..self }
+    }
+
+    /// Sorts the rows ascending by the given expression.
+    pub fn sort(self, by: Expression) -> Pipeline<'a> {
+        self.sort_by(by, false)
+    }
+
+    /// Sorts the rows descending by the given expression.
+    pub fn sort_descending(self, by: Expression) -> Pipeline<'a> {
+        self.sort_by(by, true)
+    }
+
+    fn sort_by(self, by: Expression, descending: bool) -> Pipeline<'a> {
+        let plan = Plan::Sort {
+            input: Box::new(self.plan),
+            by,
+            descending,
+        };
+        Pipeline { plan, ..self }
+    }
+
+    /// Keeps at most the given number of rows.
+    pub fn limit(self, count: usize) -> Pipeline<'a> {
+        let plan = Plan::Limit {
+            input: Box::new(self.plan),
+            count,
+        };
+        Pipeline { plan, ..self }
+    }
+
+    pub fn get_plan(&self) -> &Plan {
+        &self.plan
+    }
+
+    /// Describes how the pipeline will be executed, one step per line.
+    /// The estimated row counts are upper bounds taken from the number of
+    /// entries in the table.
+    pub fn explain(&self) -> String {
+        let mut result = String::new();
+        self.explain_plan(&self.plan, 0, &mut result);
+        result
+    }
+
+    /// Executes the pipeline and collects the resulting rows into a new table.
+    pub fn execute(&self) -> Result<Table, String> {
+        let columns = self.columns(&self.plan)?;
+        let mut result = Table::new(columns)?;
+
+        for row in self.rows(&self.plan)? {
+            result.insert(row?.get_values())?;
+        }
+
+        Ok(result)
+    }
+
+    fn push_filter(&self, plan: Plan, predicate: Expression) -> Plan {
+        match plan {
+            Plan::Scan => {
+                let mut conditions = vec![];
+                split_conjunction(predicate, &mut conditions);
+
+                match self.key_lookup(&mut conditions) {
+                    Some(keys) => {
+                        let lookup = Plan::KeyLookup { keys };
+                        match conditions.into_iter().reduce(Expression::and) {
+                            Some(rest) => Plan::Filter {
+                                input: Box::new(lookup),
+                                predicate: rest,
+                            },
+                            None => lookup,
+                        }
+                    }
+                    None => Plan::Filter {
+                        input: Box::new(Plan::Scan),
+                        predicate: conditions.into_iter().reduce(Expression::and).unwrap(),
+                    },
+                }
+            }
+            // Consecutive filters on the table are merged, so that they can
+            // become a key lookup together.
+            Plan::Filter {
+                input,
+                predicate: first,
+            } if *input == Plan::Scan => self.push_filter(Plan::Scan, first.and(predicate)),
+            plan => Plan::Filter {
+                input: Box::new(plan),
+                predicate,
+            },
+        }
+    }
+
+    // Takes the conditions fixing every key column to a constant of the right
+    // type out of the given conditions. Returns the key values, if there are any.
+    fn key_lookup(&self, conditions: &mut Vec<Expression>) -> Option<Vec<Value>> {
+        let key_columns: Vec<&Column> = self.table.columns.iter().filter(|c| c.is_key).collect();
+        if key_columns.is_empty() {
+            return None;
+        }
+
+        let mut keys = vec![];
+        let mut used = vec![];
+        for column in key_columns {
+            let position = conditions.iter().position(|c| {
+                matches!(key_equality(c), Some((name, value))
+                    if name == &column.name && value.get_type() == column.get_type())
+            })?;
+            keys.push(key_equality(&conditions[position]).unwrap().1.clone());
+            used.push(position);
+        }
+
+        let mut index = 0;
+        conditions.retain(|_| {
+            index += 1;
+            !used.contains(&(index - 1))
+        });
+
+        Some(keys)
+    }
+
+    fn columns(&self, plan: &Plan) -> Result<Vec<Column>, String> {
+        match plan {
+            Plan::Scan | Plan::KeyLookup { .. } => Ok(self.table.columns.clone()),
+            Plan::Filter { input, predicate } => {
+                let columns = self.columns(input)?;
+                predicate.get_type(&columns)?;
+                Ok(columns)
+            }
+            Plan::Project { input, columns } => {
+                let input_columns = self.columns(input)?;
+                columns
+                    .iter()
+                    .map(|name| {
+                        input_columns
+                            .iter()
+                            .find(|c| &c.name == name)
+                            .map(|c| Column::new(name.clone(), c.get_type()))
+                            .ok_or_else(|| format!("Unknown column: {}", name))
+                    })
+                    .collect()
+            }
+            Plan::Sort { input, by, .. } => {
+                let columns = self.columns(input)?;
+                by.get_type(&columns)?;
+                Ok(columns)
+            }
+            Plan::Limit { input, .. } => self.columns(input),
+        }
+    }
+
+    fn rows(&self, plan: &Plan) -> Result<Rows<'a>, String> {
+        let table: &'a Table = self.table;
+
+        Ok(match plan {
+            Plan::Scan => Box::new(table.entries.iter().map(|e| Ok(Cow::Borrowed(e)))),
+            Plan::KeyLookup { keys } => {
+                let keys = keys.clone();
+                // Keys are unique, so the search can stop at the first match.
+                let entry = table.entries.iter().find(move |e| {
+                    e.values
+                        .iter()
+                        .filter(|(c, _)| c.is_key)
+                        .map(|(_, v)| v)
+                        .eq(keys.iter())
+                });
+                Box::new(entry.into_iter().map(|e| Ok(Cow::Borrowed(e))))
+            }
+            Plan::Filter { input, predicate } => {
+                let predicate = predicate.clone();
+                Box::new(self.rows(input)?.filter_map(move |row| match row {
+                    Ok(row) => match predicate.is_true(&row) {
+                        Ok(true) => Some(Ok(row)),
+                        Ok(false) => None,
+                        Err(e) => Some(Err(e)),
+                    },
+                    Err(e) => Some(Err(e)),
+                }))
+            }
+            Plan::Project { input, .. } => {
+                let columns = self.columns(plan)?;
+                Box::new(self.rows(input)?.map(move |row| {
+                    let row = row?;
+                    let values = columns
+                        .iter()
+                        .map(|c| Expression::column(&c.name).evaluate(&row))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(Cow::Owned(Entry::new(
+                        columns.iter().cloned().zip(values).collect(),
+                    )))
+                }))
+            }
+            Plan::Sort {
+                input,
+                by,
+                descending,
+            } => {
+                let mut keyed = vec![];
+                for row in self.rows(input)? {
+                    let row = row?;
+                    keyed.push((by.evaluate(&row)?, row));
+                }
+                keyed.sort_by(
+                    |(a, _), (b, _)| {
+                        if *descending {
+                            b.cmp(a)
+                        } else {
+                            a.cmp(b)
+                        }
+                    },
+                );
+                Box::new(keyed.into_iter().map(|(_, row)| Ok(row)))
+            }
+            Plan::Limit { input, count } => Box::new(self.rows(input)?.take(*count)),
+        })
+    }
+
+    fn estimated_rows(&self, plan: &Plan) -> usize {
+        match plan {
+            Plan::Scan => self.table.entries.len(),
+            Plan::KeyLookup { .. } => self.table.entries.len().min(1),
+            Plan::Filter { input, .. } | Plan::Project { input, .. } | Plan::Sort { input, .. } => {
+                self.estimated_rows(input)
+            }
+            Plan::Limit { input, count } => self.estimated_rows(input).min(*count),
+        }
+    }
+
+    fn explain_plan(&self, plan: &Plan, depth: usize, result: &mut String) {
+        let description = match plan {
+            Plan::Scan => "Full scan".to_string(),
+            Plan::KeyLookup { keys } => {
+                let conditions: Vec<String> = self
+                    .table
+                    .columns
+                    .iter()
+                    .filter(|c| c.is_key)
+                    .zip(keys)
+                    .map(|(c, v)| {
+                        Expression::column(&c.name)
+                            .equal(Expression::Literal(v.clone()))
+                            .to_string()
+                    })
+                    .collect();
+                format!("Key lookup {}", conditions.join(" AND "))
+            }
+            Plan::Filter { predicate, .. } => format!("Filter {}", predicate),
+            Plan::Project { columns, .. } => {
+                let columns: Vec<String> = columns
+                    .iter()
+                    .map(|c| Expression::column(c).to_string())
+                    .collect();
+                format!("Project {}", columns.join(", "))
+            }
+            Plan::Sort { by, descending, .. } => {
+                format!("Sort by {}{}", by, if *descending { " DESC" } else { "" })
+            }
+            Plan::Limit { count, .. } => format!("Limit {}", count),
+        };
+
+        if depth > 0 {
+            result.push_str(&"   ".repeat(depth - 1));
+            result.push_str("-> ");
+        }
+        result.push_str(&format!(
+            "{} (estimated rows: {})\n",
+            description,
+            self.estimated_rows(plan)
+        ));
+
+        match plan {
+            Plan::Scan | Plan::KeyLookup { .. } => {}
+            Plan::Filter { input, .. }
+            | Plan::Project { input, .. }
+            | Plan::Sort { input, .. }
+            | Plan::Limit { input, .. } => self.explain_plan(input, depth + 1, result),
+        }
+    }
+}
+
+// Splits a predicate at its top level ANDs.
+fn split_conjunction(predicate: Expression, conditions: &mut Vec<Expression>) {
+    match predicate {
+        Expression::Binary(left, Operator::And, right) => {
+            split_conjunction(*left, conditions);
+            split_conjunction(*right, conditions);
+        }
+        predicate => conditions.push(predicate),
+    }
+}
+
+// Returns the column and value of a condition `column = value` or `value = column`.
+fn key_equality(condition: &Expression) -> Option<(&String, &Value)> {
+    match condition {
+        Expression::Binary(left, Operator::Equal, right) => match (&**left, &**right) {
+            (Expression::Column(name), Expression::Literal(value))
+            | (Expression::Literal(value), Expression::Column(name)) => Some((name, value)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Pipeline, Plan};
+    use crate::column::Column;
+    use crate::expression::Expression;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+
+    fn create_test_table() -> Table {
+        let column1 = Column::key("First Name", ColumnType::String);
+        let column2 = Column::key("Last Name", ColumnType::String);
+        let column3 = Column::new("Age", ColumnType::Integer);
+        let mut table = Table::new(vec![column1, column2, column3]).unwrap();
+
+        table
+            .insert(vec!["Peter".into(), "Pan".into(), 15.into()])
+            .unwrap();
+        table
+            .insert(vec!["Wendy".into(), "Darling".into(), 12.into()])
+            .unwrap();
+        table
+            .insert(vec!["James".into(), "Hook".into(), 40.into()])
+            .unwrap();
+
+        table
+    }
+
+    #[test]
+    fn explain_full_scan() {
+        let table = create_test_table();
+
+        let pipeline = Pipeline::scan(&table)
+            .filter(Expression::column("Age").greater(Expression::literal(13)))
+            .sort_descending(Expression::column("Age"))
+            .project(&["Last Name"])
+            .limit(2);
+
+        assert_eq!(
+            pipeline.explain(),
+            r#"Limit 2 (estimated rows: 2)
+-> Project "Last Name" (estimated rows: 3)
+   -> Sort by Age DESC (estimated rows: 3)
+      -> Filter (Age > 13) (estimated rows: 3)
+         -> Full scan (estimated rows: 3)
+"#
+        );
+    }
+
+    #[test]
+    fn explain_key_lookup() {
+        let table = create_test_table();
+
+        let pipeline = Pipeline::scan(&table)
+            .filter(Expression::column("Last Name").equal(Expression::literal("Pan")))
+            .filter(
+                Expression::literal("Peter")
+                    .equal(Expression::column("First Name"))
+                    .and(Expression::column("Age").greater(Expression::literal(10))),
+            );
+
+        assert_eq!(
+            pipeline.get_plan(),
+            &Plan::Filter {
+                input: Box::new(Plan::KeyLookup {
+                    keys: vec!["Peter".into(), "Pan".into()]
+                }),
+                predicate: Expression::column("Age").greater(Expression::literal(10))
+            }
+        );
+        assert_eq!(
+            pipeline.explain(),
+            r#"Filter (Age > 10) (estimated rows: 1)
+-> Key lookup ("First Name" = 'Peter') AND ("Last Name" = 'Pan') (estimated rows: 1)
+"#
+        );
+        assert_eq!(
+            pipeline.execute().unwrap().get_entries()[0].get_values(),
+            vec!["Peter".into(), "Pan".into(), 15.into()]
+        );
+    }
+
+    #[test]
+    fn partial_key_is_no_lookup() {
+        let table = create_test_table();
+
+        let pipeline = Pipeline::scan(&table)
+            .filter(Expression::column("First Name").equal(Expression::literal("Peter")));
+
+        assert!(matches!(pipeline.get_plan(), Plan::Filter { input, .. } if **input == Plan::Scan));
+    }
+
+    #[test]
+    fn execute() {
+        let table = create_test_table();
+
+        let result = Pipeline::scan(&table)
+            .filter(Expression::column("Age").less(Expression::literal(20)))
+            .sort(Expression::column("Age"))
+            .project(&["Age", "First Name"])
+            .execute()
+            .unwrap();
+
+        assert_eq!(
+            result.get_entries()[0].get_values(),
+            vec![12.into(), "Wendy".into()]
+        );
+        assert_eq!(result.get_entries().len(), 2);
+        // The table itself is untouched
+        assert_eq!(table.get_entries().len(), 3);
+    }
+
+    #[test]
+    fn execute_errors() {
+        let table = create_test_table();
+
+        assert_eq!(
+            Pipeline::scan(&table).project(&["Height"]).execute(),
+            Err("Unknown column: Height".to_string())
+        );
+        assert!(Pipeline::scan(&table)
+            .filter(Expression::column("Age").equal(Expression::literal("old")))
+            .execute()
+            .is_err());
+    }
+}
@@ -0,0 +1,639 @@
+use crate::column::Column;
+use crate::entry::Entry;
+use crate::expression::Expression;
+use crate::query::{
+    Aggregate, CreateTable, Delete, Insert, QueryResult, Select, SelectItem, Statement, Update,
+};
+use crate::table::Table;
+use crate::types::ColumnType;
+use crate::values::Value;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+pub(crate) fn execute(
+    tables: &mut HashMap<String, Table>,
+    statement: &Statement,
+) -> Result<QueryResult, String> {
+    match statement {
+        Statement::Select(select) => execute_select(tables, select).map(QueryResult::Rows),
+        Statement::Insert(insert) => execute_insert(tables, insert).map(QueryResult::Affected),
+        Statement::Update(update) => execute_update(tables, update).map(QueryResult::Affected),
+        Statement::Delete(delete) => execute_delete(tables, delete).map(QueryResult::Affected),
+        Statement::CreateTable(create) => {
+            execute_create_table(tables, create).map(|_| QueryResult::Created)
+        }
+    }
+}
+
+// The columns available while executing a select.
+// Every column is internally named `qualifier.name`, where the qualifier is the
+// alias or the name of the table the column is from.
+struct Scope {
+    columns: Vec<Column>,
+    qualifiers: Vec<String>,
+    names: Vec<String>,
+}
+
+impl Scope {
+    fn new() -> Scope {
+        Scope {
+            columns: vec![],
+            qualifiers: vec![],
+            names: vec![],
+        }
+    }
+
+    fn add_table(&mut self, qualifier: &str, table: &Table) -> Result<(), String> {
+        if self.qualifiers.iter().any(|q| q == qualifier) {
+            return Err(format!("Table name used more than once: {}", qualifier));
+        }
+
+        for column in &table.columns {
+            self.columns.push(Column::new(
+                format!("{}.{}", qualifier, column.name),
+                column.get_type(),
+            ));
+            self.qualifiers.push(qualifier.to_string());
+            self.names.push(column.name.clone());
+        }
+
+        Ok(())
+    }
+
+    // Finds the column a reference in a query refers to, either by its name or
+    // by its qualified name.
+    fn lookup(&self, reference: &str) -> Result<usize, String> {
+        let candidates: Vec<usize> = (0..self.columns.len())
+            .filter(|&i| self.names[i] == reference || self.columns[i].name == reference)
+            .collect();
+
+        match candidates.as_slice() {
+            [i] => Ok(*i),
+            [] => Err(format!("Unknown column: {}", reference)),
+            _ => Err(format!("Ambiguous column: {}", reference)),
+        }
+    }
+
+    // Rewrites all column references to the internal column names.
+    fn resolve(&self, expression: &Expression) -> Result<Expression, String> {
+        Ok(match expression {
+            Expression::Column(reference) => {
+                Expression::Column(self.columns[self.lookup(reference)?].name.clone())
+            }
+            Expression::Literal(v) => Expression::Literal(v.clone()),
+            Expression::Parameter(p) => Expression::Parameter(p.clone()),
+            Expression::Binary(left, op, right) => Expression::Binary(
+                Box::new(self.resolve(left)?),
+                *op,
+                Box::new(self.resolve(right)?),
+            ),
+            Expression::Not(inner) => Expression::Not(Box::new(self.resolve(inner)?)),
+        })
+    }
+
+    // The name of the column in a result, qualified only if the name is ambiguous.
+    fn output_name(&self, index: usize) -> String {
+        if self
+            .names
+            .iter()
+            .filter(|n| **n == self.names[index])
+            .count()
+            > 1
+        {
+            self.columns[index].name.clone()
+        } else {
+            self.names[index].clone()
+        }
+    }
+}
+
+fn get_table<'a>(tables: &'a HashMap<String, Table>, name: &str) -> Result<&'a Table, String> {
+    tables
+        .get(name)
+        .ok_or_else(|| format!("Unknown table: {}", name))
+}
+
+// Creates an entry of the scope from the values of a table.
+fn scoped_entry(columns: &[Column], values: Vec<Value>) -> Entry {
+    Entry::new(columns.iter().cloned().zip(values).collect())
+}
+
+fn execute_select(tables: &HashMap<String, Table>, select: &Select) -> Result<Table, String> {
+    let mut scope = Scope::new();
+
+    let from = get_table(tables, &select.from.name)?;
+    scope.add_table(
+        select.from.alias.as_ref().unwrap_or(&select.from.name),
+        from,
+    )?;
+    let mut rows: Vec<Entry> = from
+        .entries
+        .iter()
+        .map(|e| scoped_entry(&scope.columns, e.get_values()))
+        .collect();
+
+    for join in &select.joins {
+        let table = get_table(tables, &join.table.name)?;
+        scope.add_table(join.table.alias.as_ref().unwrap_or(&join.table.name), table)?;
+
+        let on = scope.resolve(&join.on)?;
+        on.get_type(&scope.columns)?;
+
+        let mut joined = vec![];
+        for left in &rows {
+            for right in &table.entries {
+                let mut values = left.get_values();
+                values.extend(right.get_values());
+                let entry = scoped_entry(&scope.columns, values);
+                if on.is_true(&entry)? {
+                    joined.push(entry);
+                }
+            }
+        }
+        rows = joined;
+    }
+
+    if let Some(filter) = &select.filter {
+        let filter = scope.resolve(filter)?;
+        filter.get_type(&scope.columns)?;
+
+        let mut filtered = vec![];
+        for row in rows {
+            if filter.is_true(&row)? {
+                filtered.push(row);
+            }
+        }
+        rows = filtered;
+    }
+
+    let is_grouped = !select.group_by.is_empty()
+        || select
+            .items
+            .iter()
+            .any(|i| matches!(i, SelectItem::Aggregate { .. }));
+
+    if is_grouped {
+        execute_grouped_select(&scope, rows, select)
+    } else {
+        execute_plain_select(&scope, rows, select)
+    }
+}
+
+// A select without aggregates. The rows are sorted before projecting them, so
+// that the ordering can use columns that are not selected.
+fn execute_plain_select(scope: &Scope, rows: Vec<Entry>, select: &Select) -> Result<Table, String> {
+    let mut outputs: Vec<(String, Expression)> = vec![];
+    for item in &select.items {
+        match item {
+            SelectItem::Wildcard => {
+                for i in 0..scope.columns.len() {
+                    outputs.push((
+                        scope.output_name(i),
+                        Expression::Column(scope.columns[i].name.clone()),
+                    ));
+                }
+            }
+            SelectItem::Expression { expression, alias } => {
+                let name = match (alias, expression) {
+                    (Some(alias), _) => alias.clone(),
+                    (None, Expression::Column(reference)) => {
+                        scope.output_name(scope.lookup(reference)?)
+                    }
+                    (None, expression) => expression.to_string(),
+                };
+                outputs.push((name, scope.resolve(expression)?));
+            }
+            SelectItem::Aggregate { .. } => unreachable!(),
+        }
+    }
+
+    // Ordering can refer to the output names of the select.
+    let mut order_by = vec![];
+    for order in &select.order_by {
+        let alias = match &order.expression {
+            Expression::Column(reference) => outputs.iter().find(|(n, _)| n == reference),
+            _ => None,
+        };
+        let expression = match alias {
+            Some((_, expression)) => expression.clone(),
+            None => scope.resolve(&order.expression)?,
+        };
+        expression.get_type(&scope.columns)?;
+        order_by.push((expression, order.descending));
+    }
+    let rows = sort_and_limit(rows, &order_by, select)?;
+
+    let mut columns = vec![];
+    for (name, expression) in &outputs {
+        columns.push(Column::new(
+            name.clone(),
+            expression.get_type(&scope.columns)?,
+        ));
+    }
+    let mut result = new_result_table(columns)?;
+
+    for row in rows {
+        let values = outputs
+            .iter()
+            .map(|(_, expression)| expression.evaluate(&row))
+            .collect::<Result<_, _>>()?;
+        result.insert(values)?;
+    }
+
+    Ok(result)
+}
+
+// The possible outputs of a grouped select.
+enum GroupOutput {
+    Scalar(Expression),
+    Aggregate(Aggregate, Option<Expression>),
+}
+
+// A select with aggregates or a GROUP BY. The rows are sorted after computing
+// the groups, so that the ordering can use the aggregates.
+fn execute_grouped_select(
+    scope: &Scope,
+    rows: Vec<Entry>,
+    select: &Select,
+) -> Result<Table, String> {
+    let group_by = select
+        .group_by
+        .iter()
+        .map(|e| scope.resolve(e))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut outputs: Vec<(String, GroupOutput)> = vec![];
+    let mut columns = vec![];
+    for item in &select.items {
+        match item {
+            SelectItem::Wildcard => {
+                return Err("* cannot be selected together with GROUP BY".to_string())
+            }
+            SelectItem::Expression { expression, alias } => {
+                let resolved = scope.resolve(expression)?;
+                if !is_grouped_expression(&resolved, &group_by) {
+                    return Err(format!(
+                        "{} must appear in GROUP BY or be used in an aggregate",
+                        expression
+                    ));
+                }
+                let name = match (alias, expression) {
+                    (Some(alias), _) => alias.clone(),
+                    (None, Expression::Column(reference)) => {
+                        scope.output_name(scope.lookup(reference)?)
+                    }
+                    (None, expression) => expression.to_string(),
+                };
+                columns.push(Column::new(
+                    name.clone(),
+                    resolved.get_type(&scope.columns)?,
+                ));
+                outputs.push((name, GroupOutput::Scalar(resolved)));
+            }
+            SelectItem::Aggregate {
+                function,
+                argument,
+                alias,
+            } => {
+                let resolved = match argument {
+                    Some(argument) => Some(scope.resolve(argument)?),
+                    None => None,
+                };
+                let argument_type = match &resolved {
+                    Some(argument) => Some(argument.get_type(&scope.columns)?),
+                    None => None,
+                };
+                let column_type = aggregate_type(*function, argument_type)?;
+                let name = match alias {
+                    Some(alias) => alias.clone(),
+                    None => aggregate_name(*function, argument.as_ref()),
+                };
+                columns.push(Column::new(name.clone(), column_type));
+                outputs.push((name, GroupOutput::Aggregate(*function, resolved)));
+            }
+        }
+    }
+
+    // Groups in order of their first row
+    let mut groups: Vec<Vec<Entry>> = vec![];
+    let mut group_indices: HashMap<Vec<Value>, usize> = HashMap::new();
+    for row in rows {
+        let key = group_by
+            .iter()
+            .map(|e| e.evaluate(&row))
+            .collect::<Result<Vec<_>, _>>()?;
+        let index = *group_indices.entry(key).or_insert_with(|| {
+            groups.push(vec![]);
+            groups.len() - 1
+        });
+        groups[index].push(row);
+    }
+    // Without GROUP BY all rows form one group, even if there are none.
+    if group_by.is_empty() && groups.is_empty() {
+        groups.push(vec![]);
+    }
+
+    let mut result_rows = vec![];
+    for group in groups {
+        let mut values = vec![];
+        for ((_, output), column) in outputs.iter().zip(&columns) {
+            values.push(match output {
+                GroupOutput::Scalar(expression) => match group.first() {
+                    Some(first) => expression.evaluate(first)?,
+                    None => expression.evaluate(&Entry::new(vec![]))?,
+                },
+                GroupOutput::Aggregate(function, argument) => {
+                    compute_aggregate(*function, argument.as_ref(), column.get_type(), &group)?
+                }
+            });
+        }
+        result_rows.push(scoped_entry(&columns, values));
+    }
+
+    // Ordering refers to the output names of the select.
+    let mut order_by = vec![];
+    for order in &select.order_by {
+        order.expression.get_type(&columns)?;
+        order_by.push((order.expression.clone(), order.descending));
+    }
+    let result_rows = sort_and_limit(result_rows, &order_by, select)?;
+
+    let mut result = new_result_table(columns)?;
+    for row in result_rows {
+        result.insert(row.get_values())?;
+    }
+
+    Ok(result)
+}
+
+// An expression can be selected in a grouped select if it is one of the group
+// expressions or only uses columns that are group expressions themselves.
+fn is_grouped_expression(expression: &Expression, group_by: &[Expression]) -> bool {
+    if group_by.contains(expression) {
+        return true;
+    }
+
+    match expression {
+        Expression::Column(_) => false,
+        Expression::Literal(_) | Expression::Parameter(_) => true,
+        Expression::Binary(left, _, right) => {
+            is_grouped_expression(left, group_by) && is_grouped_expression(right, group_by)
+        }
+        Expression::Not(inner) => is_grouped_expression(inner, group_by),
+    }
+}
+
+fn aggregate_name(function: Aggregate, argument: Option<&Expression>) -> String {
+    let function_name = match function {
+        Aggregate::Count => "COUNT",
+        Aggregate::Sum => "SUM",
+        Aggregate::Min => "MIN",
+        Aggregate::Max => "MAX",
+        Aggregate::Avg => "AVG",
+    };
+    match argument {
+        Some(argument) => format!("{}({})", function_name, argument),
+        None => format!("{}(*)", function_name),
+    }
+}
+
+fn aggregate_type(
+    function: Aggregate,
+    argument_type: Option<ColumnType>,
+) -> Result<ColumnType, String> {
+    match (function, argument_type) {
+        (Aggregate::Count, _) => Ok(ColumnType::Integer),
+        (Aggregate::Min, Some(t)) | (Aggregate::Max, Some(t)) => Ok(t),
+        (Aggregate::Sum, Some(ColumnType::Integer))
+        | (Aggregate::Avg, Some(ColumnType::Integer)) => Ok(ColumnType::Integer),
+        (_, t) => Err(format!(
+            "{} cannot be computed for {:?}",
+            aggregate_name(function, None),
+            t
+        )),
+    }
+}
+
+// Computes an aggregate over the rows of a group.
+// Null values are skipped, aggregates other than COUNT of no values are null.
+fn compute_aggregate(
+    function: Aggregate,
+    argument: Option<&Expression>,
+    result_type: ColumnType,
+    rows: &[Entry],
+) -> Result<Value, String> {
+    let argument = match (function, argument) {
+        (Aggregate::Count, None) => return Ok(Value::Integer(rows.len() as i32)),
+        (_, Some(argument)) => argument,
+        (_, None) => unreachable!(),
+    };
+
+    let mut values = vec![];
+    for row in rows {
+        let value = argument.evaluate(row)?;
+        if !value.is_null() {
+            values.push(value);
+        }
+    }
+
+    if function == Aggregate::Count {
+        return Ok(Value::Integer(values.len() as i32));
+    }
+    if values.is_empty() {
+        return Ok(Value::Null(result_type));
+    }
+
+    let integers = || {
+        values.iter().map(|v| match v {
+            Value::Integer(i) => *i as i64,
+            _ => 0,
+        })
+    };
+    let to_integer = |sum: i64| {
+        if sum < i32::MIN as i64 || sum > i32::MAX as i64 {
+            Err(format!(
+                "Integer overflow in {}",
+                aggregate_name(function, Some(argument))
+            ))
+        } else {
+            Ok(Value::Integer(sum as i32))
+        }
+    };
+
+    match function {
+        Aggregate::Count => unreachable!(),
+        Aggregate::Sum => to_integer(integers().sum()),
+        Aggregate::Avg => to_integer(integers().sum::<i64>() / values.len() as i64),
+        Aggregate::Min => Ok(values.into_iter().min().unwrap()),
+        Aggregate::Max => Ok(values.into_iter().max().unwrap()),
+    }
+}
+
+fn sort_and_limit(
+    rows: Vec<Entry>,
+    order_by: &[(Expression, bool)],
+    select: &Select,
+) -> Result<Vec<Entry>, String> {
+    let mut keyed = vec![];
+    for row in rows {
+        let key = order_by
+            .iter()
+            .map(|(e, _)| e.evaluate(&row))
+            .collect::<Result<Vec<_>, _>>()?;
+        keyed.push((key, row));
+    }
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        for (i, (_, descending)) in order_by.iter().enumerate() {
+            let ordering = if *descending {
+                b[i].cmp(&a[i])
+            } else {
+                a[i].cmp(&b[i])
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    });
+
+    Ok(keyed
+        .into_iter()
+        .map(|(_, row)| row)
+        .skip(select.offset.unwrap_or(0))
+        .take(select.limit.unwrap_or(usize::MAX))
+        .collect())
+}
+
+fn new_result_table(columns: Vec<Column>) -> Result<Table, String> {
+    if let Some(duplicate) = columns
+        .iter()
+        .enumerate()
+        .find(|(i, c)| columns[..*i].iter().any(|other| other.name == c.name))
+    {
+        return Err(format!(
+            "Column name selected more than once: {}, use AS to rename it",
+            duplicate.1.name
+        ));
+    }
+
+    Table::new(columns)
+}
+
+fn execute_insert(tables: &mut HashMap<String, Table>, insert: &Insert) -> Result<usize, String> {
+    let table = get_table(tables, &insert.table)?;
+
+    // Position in the given values for each column of the table
+    let positions: Vec<usize> = match &insert.columns {
+        None => (0..table.columns.len()).collect(),
+        Some(names) => {
+            for name in names {
+                if !table.columns.iter().any(|c| &c.name == name) {
+                    return Err(format!("Unknown column: {}", name));
+                }
+            }
+            let mut positions = vec![];
+            for column in &table.columns {
+                let matching: Vec<usize> = (0..names.len())
+                    .filter(|&i| names[i] == column.name)
+                    .collect();
+                match matching.as_slice() {
+                    [i] => positions.push(*i),
+                    [] => return Err(format!("No value given for column: {}", column.name)),
+                    _ => return Err(format!("Column given more than once: {}", column.name)),
+                }
+            }
+            positions
+        }
+    };
+
+    let mut new_table = table.clone();
+    let no_row = Entry::new(vec![]);
+    for row in &insert.rows {
+        if row.len() != positions.len() {
+            return Err(format!(
+                "Expected {} values, got {}",
+                positions.len(),
+                row.len()
+            ));
+        }
+        let values = positions
+            .iter()
+            .map(|&i| row[i].evaluate(&no_row))
+            .collect::<Result<_, _>>()?;
+        new_table.insert(values)?;
+    }
+
+    tables.insert(insert.table.clone(), new_table);
+
+    Ok(insert.rows.len())
+}
+
+// Returns for each entry of the table whether it matches the filter.
+fn matching_entries(table: &Table, filter: &Option<Expression>) -> Result<Vec<bool>, String> {
+    match filter {
+        None => Ok(vec![true; table.entries.len()]),
+        Some(filter) => {
+            filter.get_type(&table.columns)?;
+            table.entries.iter().map(|e| filter.is_true(e)).collect()
+        }
+    }
+}
+
+fn execute_update(tables: &mut HashMap<String, Table>, update: &Update) -> Result<usize, String> {
+    let table = get_table(tables, &update.table)?;
+
+    let mut assignments = vec![];
+    for (name, expression) in &update.assignments {
+        let index = table
+            .columns
+            .iter()
+            .position(|c| &c.name == name)
+            .ok_or_else(|| format!("Unknown column: {}", name))?;
+        expression.get_type(&table.columns)?;
+        assignments.push((index, expression));
+    }
+
+    let matches = matching_entries(table, &update.filter)?;
+
+    // Rebuild the table, so that keys and types are checked like on insert.
+    let mut new_table = Table::new(table.columns.clone())?;
+    for (entry, is_match) in table.entries.iter().zip(&matches) {
+        let mut values = entry.get_values();
+        if *is_match {
+            for (index, expression) in &assignments {
+                values[*index] = expression.evaluate(entry)?;
+            }
+        }
+        new_table.insert(values)?;
+    }
+
+    tables.insert(update.table.clone(), new_table);
+
+    Ok(matches.iter().filter(|m| **m).count())
+}
+
+fn execute_delete(tables: &mut HashMap<String, Table>, delete: &Delete) -> Result<usize, String> {
+    let table = get_table(tables, &delete.table)?;
+    let matches = matching_entries(table, &delete.filter)?;
+
+    let table = tables.get_mut(&delete.table).unwrap();
+    let mut matches_iter = matches.iter();
+    table.entries.retain(|_| !matches_iter.next().unwrap());
+
+    Ok(matches.iter().filter(|m| **m).count())
+}
+
+fn execute_create_table(
+    tables: &mut HashMap<String, Table>,
+    create: &CreateTable,
+) -> Result<(), String> {
+    if tables.contains_key(&create.name) {
+        return Err(format!("Table already exists: {}", create.name));
+    }
+
+    tables.insert(create.name.clone(), Table::new(create.columns.clone())?);
+
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use crate::query::ParseError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    /// An unquoted identifier or keyword, keywords are matched case insensitive.
+    Word(String),
+    /// An identifier in double quotes, never a keyword.
+    QuotedIdentifier(String),
+    String(String),
+    Integer(i64),
+    /// `?` or `?N`, with the explicit position `N` starting at 1.
+    PositionalParameter(Option<usize>),
+    /// `:name`
+    NamedParameter(String),
+    Symbol(&'static str),
+    End,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    /// Position of the first character of the token in the query, counted in characters.
+    pub(crate) position: usize,
+}
+
+// Longer symbols first, so that "<=" is not read as "<" followed by "=".
+const SYMBOLS: [&str; 17] = [
+    "<>", "!=", "<=", ">=", "||", ",", "(", ")", "*", "+", "-", "/", "=", "<", ">", ".", ";",
+];
+
+/// Splits the query into tokens. The last token is always `TokenKind::End`.
+pub(crate) fn tokenize(query: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // Line comments
+        if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        let kind = if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            TokenKind::Word(chars[start..i].iter().collect())
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let digits: String = chars[start..i].iter().collect();
+            let value = digits
+                .parse::<i64>()
+                .map_err(|_| ParseError::new("Integer literal is too large", start))?;
+            TokenKind::Integer(value)
+        } else if c == '?' {
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            if i == start + 1 {
+                TokenKind::PositionalParameter(None)
+            } else {
+                let digits: String = chars[start + 1..i].iter().collect();
+                match digits.parse::<usize>() {
+                    Ok(position) if position > 0 => TokenKind::PositionalParameter(Some(position)),
+                    _ => return Err(ParseError::new("Invalid parameter position", start)),
+                }
+            }
+        } else if c == ':' {
+            i += 1;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            if i == start + 1 {
+                return Err(ParseError::new(
+                    "Expected a parameter name after ':'",
+                    start,
+                ));
+            }
+            TokenKind::NamedParameter(chars[start + 1..i].iter().collect())
+        } else if c == '\'' || c == '"' {
+            let (content, end) = read_quoted(&chars, start)?;
+            i = end;
+            if c == '\'' {
+                TokenKind::String(content)
+            } else {
+                TokenKind::QuotedIdentifier(content)
+            }
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| {
+            s.chars()
+                .enumerate()
+                .all(|(j, sc)| chars.get(i + j) == Some(&sc))
+        }) {
+            i += symbol.chars().count();
+            TokenKind::Symbol(symbol)
+        } else {
+            return Err(ParseError::new(
+                format!("Unexpected character '{}'", c),
+                start,
+            ));
+        };
+
+        tokens.push(Token {
+            kind,
+            position: start,
+        });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        position: chars.len(),
+    });
+
+    Ok(tokens)
+}
+
+// Reads a string quoted by the character at `start`, a doubled quote character
+// stands for the quote itself. Returns the content and the index after the
+// closing quote.
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), ParseError> {
+    let quote = chars[start];
+    let mut content = String::new();
+    let mut i = start + 1;
+
+    loop {
+        match chars.get(i) {
+            None => return Err(ParseError::new("Unterminated quote", start)),
+            Some(&c) if c == quote => {
+                if chars.get(i + 1) == Some(&quote) {
+                    content.push(quote);
+                    i += 2;
+                } else {
+                    return Ok((content, i + 1));
+                }
+            }
+            Some(&c) => {
+                content.push(c);
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::TokenKind;
+
+    fn kinds(query: &str) -> Vec<TokenKind> {
+        super::tokenize(query)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.kind)
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_select() {
+        assert_eq!(
+            kinds(r#"SELECT "Last Name", Age*2 FROM people WHERE Age >= 18"#),
+            vec![
+                TokenKind::Word("SELECT".to_string()),
+                TokenKind::QuotedIdentifier("Last Name".to_string()),
+                TokenKind::Symbol(","),
+                TokenKind::Word("Age".to_string()),
+                TokenKind::Symbol("*"),
+                TokenKind::Integer(2),
+                TokenKind::Word("FROM".to_string()),
+                TokenKind::Word("people".to_string()),
+                TokenKind::Word("WHERE".to_string()),
+                TokenKind::Word("Age".to_string()),
+                TokenKind::Symbol(">="),
+                TokenKind::Integer(18),
+                TokenKind::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_quotes() {
+        assert_eq!(
+            kinds(r#"'It''s' "Say ""Hi""" -- comment"#),
+            vec![
+                TokenKind::String("It's".to_string()),
+                TokenKind::QuotedIdentifier("Say \"Hi\"".to_string()),
+                TokenKind::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_parameters() {
+        assert_eq!(
+            kinds("? ?2 :age"),
+            vec![
+                TokenKind::PositionalParameter(None),
+                TokenKind::PositionalParameter(Some(2)),
+                TokenKind::NamedParameter("age".to_string()),
+                TokenKind::End,
+            ]
+        );
+        assert_eq!(super::tokenize("a = ?0").unwrap_err().position, 4);
+        assert_eq!(super::tokenize("a = : b").unwrap_err().position, 4);
+    }
+
+    #[test]
+    fn tokenize_errors() {
+        assert_eq!(super::tokenize("SELECT 'abc").unwrap_err().position, 7);
+        assert_eq!(super::tokenize("SELECT a # b").unwrap_err().position, 9);
+    }
+}
@@ -0,0 +1,463 @@
+//! A small subset of SQL to query and modify named tables.
+//!
+//! Supported are `SELECT ... FROM ... [JOIN ... ON ...] [WHERE ...] [GROUP BY ...]
+//! [ORDER BY ...] [LIMIT ... [OFFSET ...]]`, `INSERT INTO ... VALUES ...`,
+//! `UPDATE ... SET ... [WHERE ...]`, `DELETE FROM ... [WHERE ...]` and
+//! `CREATE TABLE ... (...)`.
+//! Identifiers containing spaces or other special characters can be written in
+//! double quotes, strings are written in single quotes.
+
+mod executor;
+mod lexer;
+mod parser;
+
+use crate::column::Column;
+use crate::expression::Expression;
+use crate::table::Table;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// A parsed SQL statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Statement {
+    Select(Select),
+    Insert(Insert),
+    Update(Update),
+    Delete(Delete),
+    CreateTable(CreateTable),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Select {
+    pub items: Vec<SelectItem>,
+    pub from: TableReference,
+    pub joins: Vec<Join>,
+    pub filter: Option<Expression>,
+    pub group_by: Vec<Expression>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SelectItem {
+    /// `*`, all columns of all tables.
+    Wildcard,
+    Expression {
+        expression: Expression,
+        alias: Option<String>,
+    },
+    /// An aggregate, the argument is `None` for `COUNT(*)`.
+    Aggregate {
+        function: Aggregate,
+        argument: Option<Expression>,
+        alias: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableReference {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Join {
+    pub table: TableReference,
+    pub on: Expression,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderBy {
+    pub expression: Expression,
+    pub descending: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Insert {
+    pub table: String,
+    /// The columns the values are given for, `None` if all columns are given in table order.
+    pub columns: Option<Vec<String>>,
+    pub rows: Vec<Vec<Expression>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Update {
+    pub table: String,
+    pub assignments: Vec<(String, Expression)>,
+    pub filter: Option<Expression>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delete {
+    pub table: String,
+    pub filter: Option<Expression>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CreateTable {
+    pub name: String,
+    pub columns: Vec<Column>,
+}
+
+/// The result of executing a statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryResult {
+    /// The rows selected by a `SELECT`.
+    Rows(Table),
+    /// The number of rows inserted, updated or deleted.
+    Affected(usize),
+    /// A table was created.
+    Created,
+}
+
+/// An error while parsing a query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    /// Position of the error in the query, counted in characters starting at 0.
+    pub position: usize,
+}
+
+impl ParseError {
+    pub(crate) fn new<T: Into<String>>(message: T, position: usize) -> ParseError {
+        ParseError {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+/// An error while parsing or executing a query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryError {
+    Parse(ParseError),
+    Execution(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Parse(e) => write!(f, "Parse error: {}", e),
+            QueryError::Execution(e) => write!(f, "Execution error: {}", e),
+        }
+    }
+}
+
+impl From<ParseError> for QueryError {
+    fn from(e: ParseError) -> Self {
+        QueryError::Parse(e)
+    }
+}
+
+impl From<String> for QueryError {
+    fn from(e: String) -> Self {
+        QueryError::Execution(e)
+    }
+}
+
+/// Parses a single statement, optionally terminated by a `;`.
+pub fn parse(query: &str) -> Result<Statement, ParseError> {
+    parser::Parser::new(lexer::tokenize(query)?).parse_statement()
+}
+
+/// Parses a single expression, as used for example in a `WHERE` clause.
+pub fn parse_expression(expression: &str) -> Result<Expression, ParseError> {
+    parser::Parser::new(lexer::tokenize(expression)?).parse_standalone_expression()
+}
+
+/// Parses and executes a single statement against the given tables.
+/// Modifying statements either apply completely or leave the tables unchanged.
+pub fn execute(
+    tables: &mut HashMap<String, Table>,
+    query: &str,
+) -> Result<QueryResult, QueryError> {
+    let statement = parse(query)?;
+
+    Ok(execute_statement(tables, &statement)?)
+}
+
+/// Executes an already parsed statement against the given tables.
+pub fn execute_statement(
+    tables: &mut HashMap<String, Table>,
+    statement: &Statement,
+) -> Result<QueryResult, String> {
+    executor::execute(tables, statement)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{execute, QueryError, QueryResult};
+    use crate::table::Table;
+    use crate::values::Value;
+
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    // The people table of the examples and a table of their pets.
+    fn create_test_tables() -> HashMap<String, Table> {
+        let mut tables = HashMap::new();
+        tables.insert(
+            "people".to_string(),
+            Table::from_file(Path::new("examples/example_data")).unwrap(),
+        );
+
+        execute(
+            &mut tables,
+            "CREATE TABLE pets (name STR PRIMARY KEY, owner STR, legs INT)",
+        )
+        .unwrap();
+        execute(
+            &mut tables,
+            "INSERT INTO pets VALUES ('Nana', 'Peter', 4), ('Tinker', 'Peter', 2), ('Rex', 'Alan', 4)",
+        )
+        .unwrap();
+
+        tables
+    }
+
+    fn select(tables: &mut HashMap<String, Table>, query: &str) -> Vec<Vec<Value>> {
+        match execute(tables, query) {
+            Ok(QueryResult::Rows(table)) => {
+                table.get_entries().iter().map(|e| e.get_values()).collect()
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    fn execution_error(tables: &mut HashMap<String, Table>, query: &str) -> String {
+        match execute(tables, query) {
+            Err(QueryError::Execution(e)) => e,
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn select_all() {
+        let mut tables = create_test_tables();
+
+        let rows = select(&mut tables, "SELECT * FROM people");
+
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0], vec!["Peter".into(), "Pan".into(), 15.into()]);
+    }
+
+    #[test]
+    fn select_where() {
+        let mut tables = create_test_tables();
+
+        assert_eq!(
+            select(
+                &mut tables,
+                r#"SELECT "Last Name" FROM people WHERE Age > 30 AND NOT "First, Name" = 'Alan'"#
+            ),
+            vec![vec!["Mullins".into()], vec!["Robb".into()]]
+        );
+    }
+
+    #[test]
+    fn select_computed_columns() {
+        let mut tables = create_test_tables();
+
+        let result = execute(
+            &mut tables,
+            r#"select "First, Name" || ' ' || "Last Name" as full_name, Age * 12 months
+               from people where Age < 30"#,
+        );
+
+        let table = match result {
+            Ok(QueryResult::Rows(table)) => table,
+            other => panic!("Unexpected result {:?}", other),
+        };
+        assert_eq!(
+            table.columns,
+            vec![
+                crate::column::Column::new("full_name", crate::types::ColumnType::String),
+                crate::column::Column::new("months", crate::types::ColumnType::Integer),
+            ]
+        );
+        assert_eq!(
+            table.get_entries()[1].get_values(),
+            vec!["Alf Ma,son".into(), 300.into()]
+        );
+    }
+
+    #[test]
+    fn order_by_and_limit() {
+        let mut tables = create_test_tables();
+
+        assert_eq!(
+            select(
+                &mut tables,
+                "SELECT \"Last Name\" FROM people ORDER BY Age DESC LIMIT 2 OFFSET 1"
+            ),
+            vec![vec!["Herb".into()], vec!["Mullins".into()]]
+        );
+        assert_eq!(
+            select(
+                &mut tables,
+                "SELECT Age - 40 AS diff FROM people ORDER BY diff LIMIT 1"
+            ),
+            vec![vec![(-25).into()]]
+        );
+    }
+
+    #[test]
+    fn aggregates() {
+        let mut tables = create_test_tables();
+
+        assert_eq!(
+            select(
+                &mut tables,
+                "SELECT COUNT(*), SUM(Age), MIN(Age), MAX(\"Last Name\"), AVG(Age) FROM people"
+            ),
+            vec![vec![
+                5.into(),
+                154.into(),
+                15.into(),
+                "Robb".into(),
+                30.into()
+            ]]
+        );
+        assert_eq!(
+            select(&mut tables, "SELECT COUNT(*) FROM people WHERE Age > 100"),
+            vec![vec![0.into()]]
+        );
+    }
+
+    #[test]
+    fn join_and_group_by() {
+        let mut tables = create_test_tables();
+
+        assert_eq!(
+            select(
+                &mut tables,
+                r#"SELECT p."Last Name", COUNT(*) AS pets, SUM(legs) AS legs
+                   FROM people p JOIN pets ON pets.owner = p."First, Name"
+                   GROUP BY p."Last Name"
+                   ORDER BY pets DESC"#
+            ),
+            vec![
+                vec!["Pan".into(), 2.into(), 6.into()],
+                vec!["Herb".into(), 1.into(), 4.into()]
+            ]
+        );
+        assert_eq!(
+            select(
+                &mut tables,
+                "SELECT name, Age FROM pets INNER JOIN people ON owner = \"First, Name\" WHERE legs = 2"
+            ),
+            vec![vec!["Tinker".into(), 15.into()]]
+        );
+    }
+
+    #[test]
+    fn insert_update_delete() {
+        let mut tables = create_test_tables();
+
+        assert_eq!(
+            execute(
+                &mut tables,
+                r#"INSERT INTO people (Age, "Last Name", "First, Name") VALUES (9, 'Darling', 'Wendy')"#
+            ),
+            Ok(QueryResult::Affected(1))
+        );
+        assert_eq!(
+            execute(
+                &mut tables,
+                "UPDATE people SET Age = Age + 1 WHERE Age < 20"
+            ),
+            Ok(QueryResult::Affected(2))
+        );
+        assert_eq!(
+            select(&mut tables, "SELECT Age FROM people WHERE Age < 20"),
+            vec![vec![16.into()], vec![10.into()]]
+        );
+        assert_eq!(
+            execute(&mut tables, "DELETE FROM people WHERE Age >= 35"),
+            Ok(QueryResult::Affected(3))
+        );
+        assert_eq!(tables["people"].get_entries().len(), 3);
+    }
+
+    #[test]
+    fn failing_statements_leave_tables_unchanged() {
+        let mut tables = create_test_tables();
+        let people = tables["people"].clone();
+
+        // Duplicate key
+        assert!(execute(
+            &mut tables,
+            "UPDATE people SET \"First, Name\" = 'Peter', \"Last Name\" = 'Pan'"
+        )
+        .is_err());
+        // Second row has the wrong type
+        assert!(execute(
+            &mut tables,
+            "INSERT INTO people VALUES ('Tiger', 'Lily', 12), ('John', 'Darling', 'ten')"
+        )
+        .is_err());
+
+        assert_eq!(tables["people"], people);
+    }
+
+    #[test]
+    fn execution_errors() {
+        let mut tables = create_test_tables();
+
+        assert_eq!(
+            execution_error(&mut tables, "SELECT * FROM animals"),
+            "Unknown table: animals"
+        );
+        assert_eq!(
+            execution_error(&mut tables, "SELECT Height FROM people"),
+            "Unknown column: Height"
+        );
+        assert_eq!(
+            execution_error(
+                &mut tables,
+                "SELECT name FROM pets JOIN pets p ON pets.name = p.name"
+            ),
+            "Ambiguous column: name"
+        );
+        assert_eq!(
+            execution_error(&mut tables, "SELECT owner, legs FROM pets GROUP BY owner"),
+            "legs must appear in GROUP BY or be used in an aggregate"
+        );
+        assert_eq!(
+            execution_error(&mut tables, "SELECT Age FROM people WHERE \"Last Name\""),
+            "Condition must be an Integer, got String"
+        );
+        assert_eq!(
+            execution_error(&mut tables, "CREATE TABLE pets (name STR)"),
+            "Table already exists: pets"
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let mut tables = create_test_tables();
+
+        match execute(&mut tables, "SELECT Age FROM people WHERE") {
+            Err(QueryError::Parse(e)) => assert_eq!(e.position, 28),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
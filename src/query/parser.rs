@@ -0,0 +1,742 @@
+use crate::column::Column;
+use crate::expression::{Expression, Operator};
+use crate::query::lexer::{Token, TokenKind};
+use crate::query::{
+    Aggregate, CreateTable, Delete, Insert, Join, OrderBy, ParseError, Select, SelectItem,
+    Statement, TableReference, Update,
+};
+use crate::types::ColumnType;
+use crate::values::Value;
+
+// Words that cannot be used as unquoted identifiers.
+const RESERVED: [&str; 27] = [
+    "SELECT", "FROM", "WHERE", "GROUP", "BY", "ORDER", "LIMIT", "OFFSET", "JOIN", "INNER", "ON",
+    "AS", "AND", "OR", "NOT", "INSERT", "INTO", "VALUES", "UPDATE", "SET", "DELETE", "CREATE",
+    "TABLE", "ASC", "DESC", "KEY", "PRIMARY",
+];
+
+pub(crate) struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+    // Position of the next `?` parameter without explicit position.
+    next_parameter: usize,
+}
+
+impl Parser {
+    pub(crate) fn new(tokens: Vec<Token>) -> Parser {
+        Parser {
+            tokens,
+            current: 0,
+            next_parameter: 0,
+        }
+    }
+
+    pub(crate) fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        let statement = if self.is_keyword("SELECT") {
+            Statement::Select(self.parse_select()?)
+        } else if self.is_keyword("INSERT") {
+            Statement::Insert(self.parse_insert()?)
+        } else if self.is_keyword("UPDATE") {
+            Statement::Update(self.parse_update()?)
+        } else if self.is_keyword("DELETE") {
+            Statement::Delete(self.parse_delete()?)
+        } else if self.is_keyword("CREATE") {
+            Statement::CreateTable(self.parse_create_table()?)
+        } else {
+            return Err(self.error("Expected SELECT, INSERT, UPDATE, DELETE or CREATE"));
+        };
+
+        self.accept_symbol(";");
+        if self.peek().kind != TokenKind::End {
+            return Err(self.error("Expected end of query"));
+        }
+
+        Ok(statement)
+    }
+
+    /// Parses a single expression that must span all remaining tokens.
+    pub(crate) fn parse_standalone_expression(&mut self) -> Result<Expression, ParseError> {
+        let expression = self.parse_expression()?;
+        if self.peek().kind != TokenKind::End {
+            return Err(self.error("Expected end of expression"));
+        }
+        Ok(expression)
+    }
+
+    fn parse_select(&mut self) -> Result<Select, ParseError> {
+        self.expect_keyword("SELECT")?;
+
+        let mut items = vec![self.parse_select_item()?];
+        while self.accept_symbol(",") {
+            items.push(self.parse_select_item()?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let from = self.parse_table_reference()?;
+
+        let mut joins = vec![];
+        loop {
+            if self.accept_keyword("INNER") {
+                self.expect_keyword("JOIN")?;
+            } else if !self.accept_keyword("JOIN") {
+                break;
+            }
+            let table = self.parse_table_reference()?;
+            self.expect_keyword("ON")?;
+            let on = self.parse_expression()?;
+            joins.push(Join { table, on });
+        }
+
+        let filter = if self.accept_keyword("WHERE") {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+
+        let mut group_by = vec![];
+        if self.accept_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by.push(self.parse_expression()?);
+            while self.accept_symbol(",") {
+                group_by.push(self.parse_expression()?);
+            }
+        }
+
+        let mut order_by = vec![];
+        if self.accept_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let expression = self.parse_expression()?;
+                let descending = if self.accept_keyword("DESC") {
+                    true
+                } else {
+                    self.accept_keyword("ASC");
+                    false
+                };
+                order_by.push(OrderBy {
+                    expression,
+                    descending,
+                });
+                if !self.accept_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let mut limit = None;
+        let mut offset = None;
+        if self.accept_keyword("LIMIT") {
+            limit = Some(self.parse_count()?);
+            if self.accept_keyword("OFFSET") {
+                offset = Some(self.parse_count()?);
+            }
+        }
+
+        Ok(Select {
+            items,
+            from,
+            joins,
+            filter,
+            group_by,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn parse_select_item(&mut self) -> Result<SelectItem, ParseError> {
+        if self.accept_symbol("*") {
+            return Ok(SelectItem::Wildcard);
+        }
+
+        let aggregate = match (&self.peek().kind, &self.peek_next().kind) {
+            (TokenKind::Word(word), TokenKind::Symbol("(")) => aggregate_from_name(word),
+            _ => None,
+        };
+
+        if let Some(function) = aggregate {
+            self.advance();
+            self.expect_symbol("(")?;
+            let argument = if function == Aggregate::Count && self.accept_symbol("*") {
+                None
+            } else {
+                Some(self.parse_expression()?)
+            };
+            self.expect_symbol(")")?;
+            let alias = self.parse_alias()?;
+
+            Ok(SelectItem::Aggregate {
+                function,
+                argument,
+                alias,
+            })
+        } else {
+            let expression = self.parse_expression()?;
+            let alias = self.parse_alias()?;
+
+            Ok(SelectItem::Expression { expression, alias })
+        }
+    }
+
+    fn parse_alias(&mut self) -> Result<Option<String>, ParseError> {
+        if self.accept_keyword("AS") {
+            return Ok(Some(self.parse_identifier()?));
+        }
+        if self.is_identifier() {
+            return Ok(Some(self.parse_identifier()?));
+        }
+        Ok(None)
+    }
+
+    fn parse_table_reference(&mut self) -> Result<TableReference, ParseError> {
+        let name = self.parse_identifier()?;
+        let alias = self.parse_alias()?;
+
+        Ok(TableReference { name, alias })
+    }
+
+    fn parse_insert(&mut self) -> Result<Insert, ParseError> {
+        self.expect_keyword("INSERT")?;
+        self.expect_keyword("INTO")?;
+        let table = self.parse_identifier()?;
+
+        let columns = if self.accept_symbol("(") {
+            let columns = self.parse_identifier_list()?;
+            self.expect_symbol(")")?;
+            Some(columns)
+        } else {
+            None
+        };
+
+        self.expect_keyword("VALUES")?;
+        let mut rows = vec![];
+        loop {
+            self.expect_symbol("(")?;
+            let mut row = vec![self.parse_expression()?];
+            while self.accept_symbol(",") {
+                row.push(self.parse_expression()?);
+            }
+            self.expect_symbol(")")?;
+            rows.push(row);
+
+            if !self.accept_symbol(",") {
+                break;
+            }
+        }
+
+        Ok(Insert {
+            table,
+            columns,
+            rows,
+        })
+    }
+
+    fn parse_update(&mut self) -> Result<Update, ParseError> {
+        self.expect_keyword("UPDATE")?;
+        let table = self.parse_identifier()?;
+        self.expect_keyword("SET")?;
+
+        let mut assignments = vec![];
+        loop {
+            let column = self.parse_identifier()?;
+            self.expect_symbol("=")?;
+            assignments.push((column, self.parse_expression()?));
+
+            if !self.accept_symbol(",") {
+                break;
+            }
+        }
+
+        let filter = if self.accept_keyword("WHERE") {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+
+        Ok(Update {
+            table,
+            assignments,
+            filter,
+        })
+    }
+
+    fn parse_delete(&mut self) -> Result<Delete, ParseError> {
+        self.expect_keyword("DELETE")?;
+        self.expect_keyword("FROM")?;
+        let table = self.parse_identifier()?;
+
+        let filter = if self.accept_keyword("WHERE") {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+
+        Ok(Delete { table, filter })
+    }
+
+    fn parse_create_table(&mut self) -> Result<CreateTable, ParseError> {
+        self.expect_keyword("CREATE")?;
+        self.expect_keyword("TABLE")?;
+        let name = self.parse_identifier()?;
+        self.expect_symbol("(")?;
+
+        let mut columns: Vec<Column> = vec![];
+        loop {
+            if self.accept_keyword("PRIMARY") {
+                self.expect_keyword("KEY")?;
+                self.expect_symbol("(")?;
+                let position = self.peek().position;
+                for key in self.parse_identifier_list()? {
+                    match columns.iter_mut().find(|c| c.name == key) {
+                        Some(column) => column.is_key = true,
+                        None => {
+                            return Err(ParseError::new(
+                                format!("Unknown key column: {}", key),
+                                position,
+                            ))
+                        }
+                    }
+                }
+                self.expect_symbol(")")?;
+            } else {
+                let column_name = self.parse_identifier()?;
+                let column_type = self.parse_column_type()?;
+                let is_key = if self.accept_keyword("PRIMARY") {
+                    self.expect_keyword("KEY")?;
+                    true
+                } else {
+                    self.accept_keyword("KEY")
+                };
+
+                columns.push(if is_key {
+                    Column::key(column_name, column_type)
+                } else {
+                    Column::new(column_name, column_type)
+                });
+            }
+
+            if !self.accept_symbol(",") {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+
+        Ok(CreateTable { name, columns })
+    }
+
+    fn parse_column_type(&mut self) -> Result<ColumnType, ParseError> {
+        let column_type = match &self.peek().kind {
+            TokenKind::Word(word) => match word.to_uppercase().as_str() {
+                "INT" | "INTEGER" => Some(ColumnType::Integer),
+                "STR" | "STRING" | "TEXT" | "VARCHAR" => Some(ColumnType::String),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match column_type {
+            Some(column_type) => {
+                self.advance();
+                // Lengths like in VARCHAR(20) are accepted, but not enforced.
+                if column_type == ColumnType::String && self.accept_symbol("(") {
+                    self.parse_count()?;
+                    self.expect_symbol(")")?;
+                }
+                Ok(column_type)
+            }
+            None => Err(self.error("Expected a column type (INT or STR)")),
+        }
+    }
+
+    fn parse_identifier_list(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut identifiers = vec![self.parse_identifier()?];
+        while self.accept_symbol(",") {
+            identifiers.push(self.parse_identifier()?);
+        }
+        Ok(identifiers)
+    }
+
+    fn parse_count(&mut self) -> Result<usize, ParseError> {
+        match self.peek().kind {
+            TokenKind::Integer(i) => {
+                self.advance();
+                Ok(i as usize)
+            }
+            _ => Err(self.error("Expected a non-negative integer")),
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_and()?;
+        while self.accept_keyword("OR") {
+            left = left.or(self.parse_and()?);
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_not()?;
+        while self.accept_keyword("AND") {
+            left = left.and(self.parse_not()?);
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expression, ParseError> {
+        if self.accept_keyword("NOT") {
+            Ok(!self.parse_not()?)
+        } else {
+            self.parse_comparison()
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expression, ParseError> {
+        let left = self.parse_additive()?;
+
+        let operator = match self.peek().kind {
+            TokenKind::Symbol("=") => Operator::Equal,
+            TokenKind::Symbol("<>") | TokenKind::Symbol("!=") => Operator::NotEqual,
+            TokenKind::Symbol("<") => Operator::Less,
+            TokenKind::Symbol("<=") => Operator::LessEqual,
+            TokenKind::Symbol(">") => Operator::Greater,
+            TokenKind::Symbol(">=") => Operator::GreaterEqual,
+            _ => return Ok(left),
+        };
+        self.advance();
+
+        let right = self.parse_additive()?;
+        Ok(Expression::Binary(
+            Box::new(left),
+            operator,
+            Box::new(right),
+        ))
+    }
+
+    fn parse_additive(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let operator = match self.peek().kind {
+                TokenKind::Symbol("+") => Operator::Add,
+                TokenKind::Symbol("-") => Operator::Subtract,
+                TokenKind::Symbol("||") => Operator::Concat,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_multiplicative()?;
+            left = Expression::Binary(Box::new(left), operator, Box::new(right));
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let operator = match self.peek().kind {
+                TokenKind::Symbol("*") => Operator::Multiply,
+                TokenKind::Symbol("/") => Operator::Divide,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_unary()?;
+            left = Expression::Binary(Box::new(left), operator, Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, ParseError> {
+        if self.accept_symbol("-") {
+            // Negative literals are folded, so that i32::MIN can be written.
+            if let TokenKind::Integer(i) = self.peek().kind {
+                let position = self.peek().position;
+                self.advance();
+                return integer_literal(-i, position);
+            }
+            let operand = self.parse_unary()?;
+            return Ok(Expression::literal(0) - operand);
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, ParseError> {
+        let token = self.peek().clone();
+
+        match token.kind {
+            TokenKind::Integer(i) => {
+                self.advance();
+                integer_literal(i, token.position)
+            }
+            TokenKind::String(s) => {
+                self.advance();
+                Ok(Expression::Literal(Value::String(s)))
+            }
+            TokenKind::PositionalParameter(position) => {
+                self.advance();
+                let position = match position {
+                    Some(position) => position - 1,
+                    None => self.next_parameter,
+                };
+                self.next_parameter = position + 1;
+                Ok(Expression::positional(position))
+            }
+            TokenKind::NamedParameter(name) => {
+                self.advance();
+                Ok(Expression::named(name))
+            }
+            TokenKind::Symbol("(") => {
+                self.advance();
+                let expression = self.parse_expression()?;
+                self.expect_symbol(")")?;
+                Ok(expression)
+            }
+            TokenKind::Word(ref word)
+                if aggregate_from_name(word).is_some()
+                    && self.peek_next().kind == TokenKind::Symbol("(") =>
+            {
+                Err(self.error("Aggregate functions are only allowed as select items"))
+            }
+            _ if self.is_identifier() => {
+                let mut name = self.parse_identifier()?;
+                if self.accept_symbol(".") {
+                    name = format!("{}.{}", name, self.parse_identifier()?);
+                }
+                Ok(Expression::Column(name))
+            }
+            _ => Err(self.error("Expected an expression")),
+        }
+    }
+
+    fn parse_identifier(&mut self) -> Result<String, ParseError> {
+        if !self.is_identifier() {
+            return Err(self.error("Expected an identifier"));
+        }
+        match self.advance().kind {
+            TokenKind::Word(word) | TokenKind::QuotedIdentifier(word) => Ok(word),
+            _ => unreachable!(),
+        }
+    }
+
+    fn is_identifier(&self) -> bool {
+        match &self.peek().kind {
+            TokenKind::Word(word) => !RESERVED.contains(&word.to_uppercase().as_str()),
+            TokenKind::QuotedIdentifier(_) => true,
+            _ => false,
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn peek_next(&self) -> &Token {
+        self.tokens
+            .get(self.current + 1)
+            .unwrap_or_else(|| &self.tokens[self.tokens.len() - 1])
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::End {
+            self.current += 1;
+        }
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        match &self.peek().kind {
+            TokenKind::Word(word) => word.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        if self.is_keyword(keyword) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.accept_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected {}", keyword)))
+        }
+    }
+
+    fn accept_symbol(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek().kind, TokenKind::Symbol(s) if s == symbol) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), ParseError> {
+        if self.accept_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{}'", symbol)))
+        }
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        let token = self.peek();
+        let found = match &token.kind {
+            TokenKind::Word(w) => w.clone(),
+            TokenKind::QuotedIdentifier(w) => format!("\"{}\"", w),
+            TokenKind::String(s) => format!("'{}'", s),
+            TokenKind::Integer(i) => i.to_string(),
+            TokenKind::PositionalParameter(Some(i)) => format!("?{}", i),
+            TokenKind::PositionalParameter(None) => "?".to_string(),
+            TokenKind::NamedParameter(name) => format!(":{}", name),
+            TokenKind::Symbol(s) => s.to_string(),
+            TokenKind::End => "end of query".to_string(),
+        };
+        ParseError::new(format!("{}, found {}", message, found), token.position)
+    }
+}
+
+fn aggregate_from_name(name: &str) -> Option<Aggregate> {
+    match name.to_uppercase().as_str() {
+        "COUNT" => Some(Aggregate::Count),
+        "SUM" => Some(Aggregate::Sum),
+        "MIN" => Some(Aggregate::Min),
+        "MAX" => Some(Aggregate::Max),
+        "AVG" => Some(Aggregate::Avg),
+        _ => None,
+    }
+}
+
+fn integer_literal(value: i64, position: usize) -> Result<Expression, ParseError> {
+    if value < i32::MIN as i64 || value > i32::MAX as i64 {
+        return Err(ParseError::new("Integer literal is out of range", position));
+    }
+    Ok(Expression::literal(value as i32))
+}
+
+#[cfg(test)]
+mod test {
+
+    use crate::expression::Expression;
+    use crate::query::{parse, Aggregate, OrderBy, SelectItem, Statement};
+
+    #[test]
+    fn parse_select() {
+        let statement = parse(
+            r#"SELECT "Last Name", COUNT(*) AS n FROM people p
+               WHERE p.Age > 10 GROUP BY "Last Name" ORDER BY n DESC LIMIT 2;"#,
+        )
+        .unwrap();
+
+        let select = match statement {
+            Statement::Select(select) => select,
+            _ => panic!("Expected a select"),
+        };
+
+        assert_eq!(
+            select.items,
+            vec![
+                SelectItem::Expression {
+                    expression: Expression::column("Last Name"),
+                    alias: None
+                },
+                SelectItem::Aggregate {
+                    function: Aggregate::Count,
+                    argument: None,
+                    alias: Some("n".to_string())
+                }
+            ]
+        );
+        assert_eq!(select.from.alias, Some("p".to_string()));
+        assert_eq!(
+            select.filter,
+            Some(Expression::column("p.Age").greater(Expression::literal(10)))
+        );
+        assert_eq!(select.group_by, vec![Expression::column("Last Name")]);
+        assert_eq!(
+            select.order_by,
+            vec![OrderBy {
+                expression: Expression::column("n"),
+                descending: true
+            }]
+        );
+        assert_eq!(select.limit, Some(2));
+    }
+
+    #[test]
+    fn parse_precedence() {
+        let statement = parse("DELETE FROM t WHERE NOT a = 1 + 2 * -3 OR b || 'x' = 'yx'").unwrap();
+
+        let expected = (!Expression::column("a")
+            .equal(Expression::literal(1) + Expression::literal(2) * Expression::literal(-3)))
+        .or(Expression::column("b")
+            .concat(Expression::literal("x"))
+            .equal(Expression::literal("yx")));
+
+        match statement {
+            Statement::Delete(delete) => assert_eq!(delete.filter, Some(expected)),
+            _ => panic!("Expected a delete"),
+        }
+    }
+
+    #[test]
+    fn parse_parameters() {
+        let statement =
+            parse("DELETE FROM t WHERE a = ? AND b = :name AND c = ? OR d = ?1").unwrap();
+
+        let expected = Expression::column("a")
+            .equal(Expression::positional(0))
+            .and(Expression::column("b").equal(Expression::named("name")))
+            .and(Expression::column("c").equal(Expression::positional(1)))
+            .or(Expression::column("d").equal(Expression::positional(0)));
+
+        match statement {
+            Statement::Delete(delete) => assert_eq!(delete.filter, Some(expected)),
+            _ => panic!("Expected a delete"),
+        }
+    }
+
+    #[test]
+    fn parse_create_table() {
+        let statement =
+            parse("CREATE TABLE t (a INT, b VARCHAR(10), c str KEY, PRIMARY KEY (a))").unwrap();
+
+        match statement {
+            Statement::CreateTable(create) => assert_eq!(
+                create.columns,
+                vec![
+                    crate::column::Column::key("a", crate::types::ColumnType::Integer),
+                    crate::column::Column::new("b", crate::types::ColumnType::String),
+                    crate::column::Column::key("c", crate::types::ColumnType::String),
+                ]
+            ),
+            _ => panic!("Expected a create table"),
+        }
+    }
+
+    #[test]
+    fn parse_error_positions() {
+        let error = parse("SELECT Age FROM").unwrap_err();
+        assert_eq!(error.position, 15);
+        assert_eq!(
+            error.to_string(),
+            "Expected an identifier, found end of query at position 15"
+        );
+
+        assert_eq!(parse("SELECT Age, FROM t").unwrap_err().position, 12);
+        assert_eq!(
+            parse("SELECT SUM(Age) + 1 FROM t").unwrap_err().position,
+            16
+        );
+        assert_eq!(parse("SELECT 3000000000 FROM t").unwrap_err().position, 7);
+        assert_eq!(parse("UPDATE t Age = 1").unwrap_err().position, 9);
+        assert_eq!(parse("DROP TABLE t").unwrap_err().position, 0);
+        assert_eq!(
+            parse("CREATE TABLE t (a INT, PRIMARY KEY (b))")
+                .unwrap_err()
+                .position,
+            36
+        );
+    }
+}
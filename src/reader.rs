@@ -0,0 +1,272 @@
+use crate::binary;
+use crate::column::Column;
+use crate::compression;
+use crate::deserialization::Header;
+use crate::encryption;
+use crate::entry::Entry;
+use crate::serializer::HEADER_PREFIX;
+use crate::table::Table;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Reads the entries of a table in the text format one at a time, without
+/// holding the whole table in memory.
+///
+/// The header and columns are read when the reader is created, the entries
+/// are then yielded in the order of the file. A corrupt row yields an error
+/// and reading continues with the next row.
+#[derive(Debug)]
+pub struct TableReader<R: BufRead> {
+    reader: R,
+    header: Header,
+    columns: Vec<Column>,
+    // The line of the next row, starting at 1.
+    line: usize,
+    // The rows read so far, including corrupt ones.
+    rows: usize,
+    is_done: bool,
+}
+
+impl TableReader<BufReader<File>> {
+    /// Opens a table file in the text format.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        TableReader::new(BufReader::new(File::open(path).map_err(|e| e.to_string())?))
+    }
+}
+
+impl<R: BufRead> TableReader<R> {
+    /// Reads the header and columns of a table in the text format.
+    /// Will error for the binary format or compressed and encrypted files.
+    pub fn new(mut reader: R) -> Result<Self, String> {
+        let start = reader.fill_buf().map_err(|e| e.to_string())?;
+        if binary::is_binary(start)
+            || compression::is_compressed(start)
+            || encryption::is_encrypted(start)
+        {
+            return Err("Only tables in the text format can be read as a stream".to_string());
+        }
+
+        let mut line = read_line(&mut reader)?.ok_or_else(|| "String is empty".to_string())?;
+        let mut line_number = 2;
+        let header = if line.starts_with(HEADER_PREFIX.as_bytes()) {
+            let header = Header::deserialize_line(&decode(line, false)?)?;
+            line = read_line(&mut reader)?.ok_or_else(|| "String is empty".to_string())?;
+            line_number += 1;
+            header
+        } else {
+            Header::NONE
+        };
+        let (columns, _) = Column::deserialize_columns(decode(line, header.version >= 2)?)?;
+        // Checks the columns.
+        Table::new(columns.clone())?;
+
+        Ok(TableReader {
+            reader,
+            header,
+            columns,
+            line: line_number,
+            rows: 0,
+            is_done: false,
+        })
+    }
+
+    pub fn get_columns(&self) -> &Vec<Column> {
+        &self.columns
+    }
+
+    /// The version of the text format, see `Table::serialize_version`.
+    pub fn get_version(&self) -> u32 {
+        self.header.version
+    }
+
+    fn next_entry(&mut self) -> Result<Option<Entry>, String> {
+        let line = match read_line(&mut self.reader)? {
+            Some(line) if !line.is_empty() => line,
+            _ => {
+                self.is_done = true;
+                return match self.header.entries {
+                    Some(expected) if expected != self.rows => Err(format!(
+                        "Expected {} entries, found {}",
+                        expected, self.rows
+                    )),
+                    _ => Ok(None),
+                };
+            }
+        };
+
+        let line_number = self.line;
+        self.line += 1;
+        self.rows += 1;
+        decode(line, self.header.version >= 2)
+            .and_then(|line| {
+                let data = self.header.verify_checksum(&line)?;
+                Entry::deserialize_data(data.to_string(), self.columns.clone())
+            })
+            .map(Some)
+            .map_err(|e| format!("Corrupt row in line {}: {}", line_number, e))
+    }
+}
+
+impl<R: BufRead> Iterator for TableReader<R> {
+    type Item = Result<Entry, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+        self.next_entry().transpose()
+    }
+}
+
+impl Table {
+    /// Deserialize a table in the text format from a reader, like
+    /// `deserialize`, without reading the whole text into memory first.
+    pub fn deserialize_from<R: Read>(reader: R) -> Result<Self, String> {
+        let reader = TableReader::new(BufReader::new(reader))?;
+        let mut table = Table::new(reader.get_columns().clone())?;
+
+        let mut entries = vec![];
+        for entry in reader {
+            entries.push(entry?.values.into_iter().map(|(_, v)| v).collect());
+        }
+        table.insert_all(entries)?;
+
+        Ok(table)
+    }
+}
+
+// None at the end of the reader.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<Vec<u8>>, String> {
+    let mut line = vec![];
+    if reader
+        .read_until(b'\n', &mut line)
+        .map_err(|e| e.to_string())?
+        == 0
+    {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    Ok(Some(line))
+}
+
+// Invalid UTF-8 is only replaced if the rows have checksums, like in
+// `Table::decode_text`, and only fails the line it is in.
+fn decode(line: Vec<u8>, lossy: bool) -> Result<String, String> {
+    match String::from_utf8(line) {
+        Ok(line) => Ok(line),
+        Err(e) if lossy => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::TableReader;
+    use crate::column::Column;
+    use crate::expression::Expression;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    use std::fs;
+
+    fn create_test_table() -> Table {
+        let mut table = Table::new(vec![
+            Column::key("Name", ColumnType::String),
+            Column::new("Age", ColumnType::Integer),
+        ])
+        .unwrap();
+        table.insert(vec!["Peter".into(), 15.into()]).unwrap();
+        table
+            .insert(vec!["Wendy, Moira".into(), 12.into()])
+            .unwrap();
+        table
+            .insert(vec!["John".into(), Value::Null(ColumnType::Integer)])
+            .unwrap();
+        table
+    }
+
+    #[test]
+    fn filter_file() {
+        let path =
+            std::env::temp_dir().join(format!("file-minidb-reader-{}.table", std::process::id()));
+        create_test_table().write_file(&path).unwrap();
+
+        let reader = TableReader::open(&path).unwrap();
+        assert_eq!(reader.get_columns(), &create_test_table().columns);
+        let condition = Expression::column("Age").less(Expression::literal(14));
+        let names: Vec<Value> = reader
+            .map(|e| e.unwrap())
+            .filter(|e| condition.is_true(e).unwrap())
+            .map(|e| e.get_values()[0].clone())
+            .collect();
+        assert_eq!(names, vec![Value::from("Wendy, Moira")]);
+
+        create_test_table().write_binary_file(&path).unwrap();
+        assert!(TableReader::open(&path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn deserialize_from() {
+        let table = create_test_table();
+        for version in 0..=2 {
+            let text = table.serialize_version(version).unwrap();
+            assert_eq!(Table::deserialize_from(text.as_bytes()), Ok(table.clone()));
+        }
+        assert!(Table::deserialize_from(&b""[..]).is_err());
+    }
+
+    #[test]
+    fn corrupt_rows() {
+        let text = create_test_table()
+            .serialize_current()
+            .replace("Peter", "Pete")
+            .replacen("\"12\"", "\"x\"", 1);
+        let results: Vec<Result<_, _>> = TableReader::new(text.as_bytes()).unwrap().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].as_ref().unwrap_err().contains("line 3"));
+        assert!(results[1].as_ref().unwrap_err().contains("line 4"));
+        assert!(results[2].is_ok());
+        assert!(Table::deserialize_from(text.as_bytes()).is_err());
+
+        let missing = create_test_table()
+            .serialize_current()
+            .replace("entries=3", "entries=4");
+        let last = TableReader::new(missing.as_bytes()).unwrap().last();
+        assert_eq!(last, Some(Err("Expected 4 entries, found 3".to_string())));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        for version in 0..=2 {
+            let mut bytes = create_test_table()
+                .serialize_version(version)
+                .unwrap()
+                .into_bytes();
+            let start = bytes.windows(5).position(|w| w == b"Peter").unwrap();
+            bytes[start + 1] = 0xff;
+
+            let results: Vec<Result<_, _>> = TableReader::new(&bytes[..]).unwrap().collect();
+            assert_eq!(results.len(), 3);
+            let error = results[0].as_ref().unwrap_err();
+            // Only replaced if the checksum of the row finds the damage.
+            if version < 2 {
+                assert!(error.contains("invalid utf-8"), "{}", error);
+            } else {
+                assert!(
+                    error.contains("Checksum") || error.contains("checksum"),
+                    "{}",
+                    error
+                );
+            }
+            assert!(results[1].is_ok());
+        }
+    }
+}
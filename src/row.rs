@@ -0,0 +1,191 @@
+use crate::column::Column;
+use crate::entry::Entry;
+use crate::table::Table;
+use crate::types::ColumnType;
+use crate::values::Value;
+
+use std::convert::TryFrom;
+
+#[cfg(feature = "derive")]
+pub use file_minidb_derive::Row;
+
+/// A type whose values are the entries of a table with fixed columns.
+///
+/// With the `derive` feature, it can be derived for structs with named fields,
+/// see `file_minidb_derive::Row`:
+///
+/// ```ignore
+/// #[derive(Row)]
+/// struct Person {
+///     #[key]
+///     name: String,
+///     age: Option<i32>,
+/// }
+/// ```
+pub trait Row: Sized {
+    /// The columns of a table with entries of this type.
+    fn schema() -> Vec<Column>;
+
+    /// The values in the order of the columns.
+    fn to_values(&self) -> Vec<Value>;
+
+    /// Creates the row from values in the order of the columns.
+    fn from_values(values: Vec<Value>) -> Result<Self, String>;
+
+    fn to_entry(&self) -> Entry {
+        Entry::new(Self::schema().into_iter().zip(self.to_values()).collect())
+    }
+
+    /// Creates the row from an entry with at least the columns of the row.
+    fn from_entry(entry: &Entry) -> Result<Self, String> {
+        Self::from_values(entry.get_values_in_order(&Self::schema())?)
+    }
+}
+
+/// A type that can be stored in a column of a `Row`.
+/// `Option` is stored as null if it is `None`.
+pub trait ColumnValue: Sized {
+    fn column_type() -> ColumnType;
+
+    fn to_value(&self) -> Value;
+
+    fn from_value(value: Value) -> Result<Self, String>;
+}
+
+impl ColumnValue for String {
+    fn column_type() -> ColumnType {
+        ColumnType::String
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.clone())
+    }
+
+    fn from_value(value: Value) -> Result<Self, String> {
+        String::try_from(value).map_err(|e| e.to_string())
+    }
+}
+
+impl ColumnValue for i32 {
+    fn column_type() -> ColumnType {
+        ColumnType::Integer
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Integer(*self)
+    }
+
+    fn from_value(value: Value) -> Result<Self, String> {
+        i32::try_from(value).map_err(|e| e.to_string())
+    }
+}
+
+impl<T: ColumnValue> ColumnValue for Option<T> {
+    fn column_type() -> ColumnType {
+        T::column_type()
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Some(value) => value.to_value(),
+            None => Value::Null(T::column_type()),
+        }
+    }
+
+    fn from_value(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Null(_) => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl Table {
+    /// Insert a row into the table, like `insert`.
+    /// Will error if the table does not have exactly the columns of the row.
+    pub fn insert_row<T: Row>(&mut self, row: &T) -> Result<(), String> {
+        if self.columns != T::schema() {
+            return Err("The columns of the row do not match the table".to_string());
+        }
+        self.insert(row.to_values())
+    }
+
+    /// The entries of the table as rows.
+    /// Every entry fails to convert if the table does not have the columns of the row.
+    pub fn iter_as<'a, T: Row + 'a>(&'a self) -> impl Iterator<Item = Result<T, String>> + 'a {
+        self.entries.iter().map(T::from_entry)
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod test {
+
+    use super::Row;
+    use crate::column::Column;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    #[derive(Row, Clone, Debug, PartialEq)]
+    struct Person {
+        #[key]
+        name: String,
+        age: Option<i32>,
+        r#type: i32,
+    }
+
+    fn peter() -> Person {
+        Person {
+            name: "Peter".to_string(),
+            age: Some(15),
+            r#type: 1,
+        }
+    }
+
+    #[test]
+    fn schema() {
+        assert_eq!(
+            Person::schema(),
+            vec![
+                Column::key("name", ColumnType::String),
+                Column::new("age", ColumnType::Integer),
+                Column::new("type", ColumnType::Integer),
+            ]
+        );
+    }
+
+    #[test]
+    fn entry_conversion() {
+        let wendy = Person {
+            name: "Wendy".to_string(),
+            age: None,
+            r#type: 2,
+        };
+        assert_eq!(
+            wendy.to_values(),
+            vec!["Wendy".into(), Value::Null(ColumnType::Integer), 2.into()]
+        );
+        assert_eq!(Person::from_entry(&wendy.to_entry()), Ok(wendy));
+        assert!(Person::from_values(vec!["Wendy".into(), 1.into()]).is_err());
+        assert!(Person::from_values(vec!["Wendy".into(), "1".into(), 1.into()]).is_err());
+        assert_eq!(
+            Person::from_values(vec!["Wendy".into(), 1.into(), 1.into(), 1.into()]),
+            Err("More values than columns".to_string())
+        );
+    }
+
+    #[test]
+    fn insert_and_iterate() {
+        let mut table = Table::new(Person::schema()).unwrap();
+        table.insert_row(&peter()).unwrap();
+        assert!(table.insert_row(&peter()).is_err());
+
+        let rows: Result<Vec<Person>, String> = table.iter_as().collect();
+        assert_eq!(rows, Ok(vec![peter()]));
+
+        let mut other = Table::new(vec![Column::key("name", ColumnType::String)]).unwrap();
+        assert!(other.insert_row(&peter()).is_err());
+        other.insert(vec!["Peter".into()]).unwrap();
+        assert!(other.iter_as::<Person>().all(|r| r.is_err()));
+    }
+}
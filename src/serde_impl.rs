@@ -0,0 +1,105 @@
+use crate::column::Column;
+use crate::table::Table;
+use crate::values::Value;
+
+use serde::de::Error;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// A table is serialized as its columns and the values of every entry.
+impl Serialize for Table {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let entries: Vec<Vec<&Value>> = self
+            .entries
+            .iter()
+            .map(|e| e.values.iter().map(|(_, v)| v).collect())
+            .collect();
+
+        let mut state = serializer.serialize_struct("Table", 2)?;
+        state.serialize_field("columns", &self.columns)?;
+        state.serialize_field("entries", &entries)?;
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Table")]
+struct TableData {
+    columns: Vec<Column>,
+    entries: Vec<Vec<Value>>,
+}
+
+// Checked like `Table::new` and `Table::insert`.
+impl<'de> Deserialize<'de> for Table {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Table, D::Error> {
+        let data = TableData::deserialize(deserializer)?;
+        let mut table = Table::new(data.columns).map_err(D::Error::custom)?;
+        table.insert_all(data.entries).map_err(D::Error::custom)?;
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use crate::column::Column;
+    use crate::entry::Entry;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    fn create_test_table() -> Table {
+        let mut table = Table::new(vec![
+            Column::key("Name", ColumnType::String),
+            Column::new("Age", ColumnType::Integer),
+        ])
+        .unwrap();
+        table.insert(vec!["Peter".into(), 15.into()]).unwrap();
+        table
+            .insert(vec!["Wendy".into(), Value::Null(ColumnType::Integer)])
+            .unwrap();
+        table
+    }
+
+    #[test]
+    fn roundtrip() {
+        let table = create_test_table();
+        let json = serde_json::to_string(&table).unwrap();
+        assert_eq!(
+            json,
+            r#"{"columns":[{"is_key":true,"name":"Name","column_type":"String"},{"is_key":false,"name":"Age","column_type":"Integer"}],"entries":[[{"String":"Peter"},{"Integer":15}],[{"String":"Wendy"},{"Null":"Integer"}]]}"#
+        );
+        assert_eq!(serde_json::from_str::<Table>(&json).unwrap(), table);
+
+        let entry = &table.get_entries()[0];
+        let json = serde_json::to_string(entry).unwrap();
+        assert_eq!(&serde_json::from_str::<Entry>(&json).unwrap(), entry);
+    }
+
+    #[test]
+    fn validation() {
+        let columns = r#"[{"is_key":true,"name":"Name","column_type":"String"}]"#;
+        let parse = |entries: &str| {
+            serde_json::from_str::<Table>(&format!(
+                r#"{{"columns":{},"entries":{}}}"#,
+                columns, entries
+            ))
+        };
+
+        assert!(parse(r#"[[{"String":"Peter"}]]"#).is_ok());
+        // Duplicate key
+        assert!(parse(r#"[[{"String":"Peter"}],[{"String":"Peter"}]]"#).is_err());
+        // Type mismatch
+        assert!(parse(r#"[[{"Integer":1}]]"#).is_err());
+        // Null key
+        assert!(parse(r#"[[{"Null":"String"}]]"#).is_err());
+        // Missing value
+        assert!(parse(r#"[[]]"#).is_err());
+        // Duplicate column
+        assert!(serde_json::from_str::<Table>(&format!(
+            r#"{{"columns":[{0},{0}],"entries":[]}}"#,
+            &columns[1..columns.len() - 1]
+        ))
+        .is_err());
+    }
+}
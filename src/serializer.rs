@@ -1,20 +1,36 @@
 use crate::column::Column;
 use crate::entry::Entry;
+use crate::hash::crc32;
 use crate::table::Table;
 use crate::types::ColumnType;
 use crate::values::Value;
 
+use std::io::Write;
+
+/// The version of the text format written by `Table::write_file`.
+pub const CURRENT_VERSION: u32 = 2;
+
+// Starts the header line, which the columns of version 0 can never start with.
+pub(crate) const HEADER_PREFIX: &str = "#file-minidb";
+
 pub trait Serializable {
     fn serialize(&self) -> String;
+
+    /// Writes the same text as `serialize` into the writer.
+    fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+        writer
+            .write_all(self.serialize().as_bytes())
+            .map_err(|e| e.to_string())
+    }
 }
 
 // Escapes all "," in the given string
-fn escape(str: &str) -> String {
+pub(crate) fn escape(str: &str) -> String {
     String::from(str).replace(",", "\\,")
 }
 
 // Will escape the string and surround it with "
-fn escape_and_surround(str: String) -> String {
+pub(crate) fn escape_and_surround(str: String) -> String {
     let mut result = String::from("\"");
     result.push_str(&escape(&str));
     result.push('\"'); // " Fix syntax highlighting
@@ -30,6 +46,76 @@ impl Serializable for Table {
         result.push_str(&self.entries.serialize());
         result
     }
+
+    // Writes the entries one at a time instead of building the whole text.
+    fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+        self.columns.serialize_into(writer)?;
+        writer.write_all(b"\n").map_err(|e| e.to_string())?;
+        self.entries.serialize_into(writer)
+    }
+}
+
+impl Table {
+    /// Writes the table in the current version of the text format into the
+    /// writer, like `write_file` and `TableWriter`, one entry at a time
+    /// instead of building the whole text. `serialize_into` writes the text of
+    /// `serialize` instead, which has no header and no checksums.
+    pub fn write_current_into<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+        writeln!(
+            writer,
+            "{} version={} entries={}",
+            HEADER_PREFIX,
+            CURRENT_VERSION,
+            self.entries.len()
+        )
+        .map_err(|e| e.to_string())?;
+        self.columns.serialize_into(writer)?;
+        writer.write_all(b"\n").map_err(|e| e.to_string())?;
+        for entry in &self.entries {
+            writeln!(writer, "{}", with_checksum(entry.serialize())).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Serialize the table in the given version of the text format.
+    ///
+    /// Version 0 is the format of `serialize` without a header. Version 1
+    /// starts with a header line holding the version and the number of
+    /// entries, e.g. `#file-minidb version=1 entries=3`. Version 2 additionally
+    /// starts every row with its CRC-32 checksum in hex, followed by a space.
+    pub fn serialize_version(&self, version: u32) -> Result<String, String> {
+        let header = format!(
+            "{} version={} entries={}\n",
+            HEADER_PREFIX,
+            version,
+            self.entries.len()
+        );
+
+        match version {
+            0 => Ok(self.serialize()),
+            1 => Ok(header + &self.serialize()),
+            2 => {
+                let mut result = header + &self.columns.serialize();
+                result.push('\n');
+                for entry in &self.entries {
+                    result.push_str(&with_checksum(entry.serialize()));
+                    result.push('\n');
+                }
+                Ok(result)
+            }
+            _ => Err(format!("Unknown format version: {}", version)),
+        }
+    }
+
+    // Serialize the table in the current version of the text format.
+    pub(crate) fn serialize_current(&self) -> String {
+        self.serialize_version(CURRENT_VERSION).unwrap()
+    }
+}
+
+// Prefixes a row with its checksum, as in version 2 of the text format.
+pub(crate) fn with_checksum(row: String) -> String {
+    format!("{:08x} {}", crc32(row.as_bytes()), row)
 }
 
 impl Serializable for Vec<Column> {
@@ -81,6 +167,14 @@ impl Serializable for Vec<Entry> {
 
         result
     }
+
+    fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+        for e in self {
+            e.serialize_into(writer)?;
+            writer.write_all(b"\n").map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
 }
 
 impl Serializable for Entry {
@@ -100,6 +194,8 @@ impl Serializable for Value {
         match self {
             Value::String(s) => escape_and_surround(s.clone()),
             Value::Integer(i) => escape_and_surround(i.clone().to_string()),
+            // Not surrounded, so that it differs from the string "null"
+            Value::Null(_) => "null".to_string(),
         }
     }
 }
@@ -114,7 +210,7 @@ mod test {
         let column2 = super::Column::new("C2", crate::types::ColumnType::String);
         let columns = vec![column1, column2];
 
-        return super::Table::new(columns).unwrap();
+        super::Table::new(columns).unwrap()
     }
 
     fn create_test_table_with_comma() -> super::Table {
@@ -122,7 +218,7 @@ mod test {
         let column2 = super::Column::new("C2,", crate::types::ColumnType::String);
         let columns = vec![column1, column2];
 
-        return super::Table::new(columns).unwrap();
+        super::Table::new(columns).unwrap()
     }
 
     #[test]
@@ -172,4 +268,27 @@ mod test {
 
         assert!(table.serialize() == serialized);
     }
+
+    #[test]
+    fn serialize_version() {
+        let mut table = create_test_table();
+
+        table.insert(vec![10.into(), "Hello".into()]).unwrap();
+
+        let serialized = r#"#file-minidb version=1 entries=1
+key int "C1",str "C2"
+"10","Hello"
+"#;
+
+        assert_eq!(table.serialize_version(1), Ok(serialized.to_string()));
+        assert_eq!(table.serialize_version(0), Ok(table.serialize()));
+        assert!(table.serialize_version(3).is_err());
+
+        let serialized = r#"#file-minidb version=2 entries=1
+key int "C1",str "C2"
+e1d55c2a "10","Hello"
+"#;
+
+        assert_eq!(table.serialize_version(2), Ok(serialized.to_string()));
+    }
 }
@@ -1,20 +1,36 @@
+use crate::binary;
 use crate::column::Column;
+use crate::compression;
+use crate::deserialization::{self, CorruptRow};
+use crate::encryption;
 use crate::entry::Entry;
-use crate::serializer::Serializable;
+use crate::expression::Expression;
+use crate::file;
+use crate::fingerprint::{self, Fingerprint};
+use crate::serializer::CURRENT_VERSION;
 use crate::values::Value;
 
 use std::collections::HashSet;
 use std::fmt;
-use std::fs::OpenOptions;
-use std::io::Write;
 use std::path::Path;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Table {
     pub(crate) columns: Vec<Column>,
     pub(crate) entries: Vec<Entry>,
+    // The fingerprint of the file the table was loaded from, see `save_if_unchanged`.
+    pub(crate) fingerprint: Option<Fingerprint>,
 }
 
+// Tables are equal by their contents, regardless of where they were loaded from.
+impl PartialEq for Table {
+    fn eq(&self, other: &Self) -> bool {
+        self.columns == other.columns && self.entries == other.entries
+    }
+}
+
+impl Eq for Table {}
+
 impl fmt::Display for Table {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for c in &self.columns {
@@ -41,37 +57,101 @@ impl Table {
         Ok(Table {
             columns,
             entries: vec![],
+            fingerprint: None,
         })
     }
 
-    /// Load table from file.
+    /// Load table from file, either in the text or in the binary format,
+    /// possibly compressed, see `write_compressed_file`.
+    /// Records the fingerprint of the file, see `save_if_unchanged`.
     pub fn from_file(path: &Path) -> Result<Table, String> {
-        let file_content = std::fs::read_to_string(path);
+        let (table, corrupt) = Table::from_file_lenient(path)?;
+
+        match corrupt.first() {
+            None => Ok(table),
+            Some(_) => Err(format!(
+                "Corrupt rows in {}, lines {}",
+                path.display(),
+                corrupt
+                    .iter()
+                    .map(|r| format!("{} ({})", r.line, r.error))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    /// Load table from file, like `from_file`, but returns rows that could not
+    /// be read or failed their checksum separately instead of failing.
+    pub fn from_file_lenient(path: &Path) -> Result<(Table, Vec<CorruptRow>), String> {
+        let (content, fingerprint) = fingerprint::read_file(path).map_err(|e| e.to_string())?;
+        let (mut table, corrupt) = Table::deserialize_file(content, path)?;
+        table.fingerprint = Some(fingerprint);
+        Ok((table, corrupt))
+    }
 
-        if let Ok(content) = file_content {
-            Table::deserialize(content)
+    // Reads the contents of the table file in any of the formats of `from_file`.
+    pub(crate) fn deserialize_file(
+        mut content: Vec<u8>,
+        path: &Path,
+    ) -> Result<(Table, Vec<CorruptRow>), String> {
+        if encryption::is_encrypted(&content) {
+            return Err(format!(
+                "Table file is encrypted, use from_encrypted_file: {}",
+                path.display()
+            ));
+        }
+        if compression::is_compressed(&content) {
+            content = compression::decompress(&content)?;
+        }
+
+        if binary::is_binary(&content) {
+            Ok((Table::deserialize_binary(&content)?, vec![]))
         } else {
-            Err(file_content.err().unwrap().to_string())
+            Table::deserialize_lenient(decode_text(content)?)
         }
     }
 
-    /// Write contents of the table to a file.
-    /// Will overwrite the file.
+    /// Write contents of the table to a file, in the current version of the
+    /// text format.
+    /// Will atomically replace the file, so that a crash never leaves a
+    /// partially written file behind.
     pub fn write_file(&self, path: &Path) -> Result<(), String> {
-        let file = OpenOptions::new()
-            .read(false)
-            .write(true)
-            .create(true)
-            .open(path);
-
-        if file.is_err() {
-            Err(file.err().unwrap().to_string())
-        } else {
-            let serialized = self.serialize();
-            write!(&mut file.unwrap(), "{}", serialized).unwrap();
+        file::write_atomic(path, self.serialize_current().as_bytes())
+    }
+
+    /// Write contents of the table to a file, like `write_file`, in the given
+    /// version of the text format, see `serialize_version`.
+    pub fn write_file_version(&self, path: &Path, version: u32) -> Result<(), String> {
+        file::write_atomic(path, self.serialize_version(version)?.as_bytes())
+    }
 
-            Ok(())
+    /// Rewrites a table file in an older version of the text format in the
+    /// current version. Returns if the file was rewritten.
+    /// Files in the binary format are left unchanged, compressed files stay
+    /// compressed.
+    pub fn migrate_file(path: &Path) -> Result<bool, String> {
+        let table = Table::from_file(path)?;
+
+        let (mut content, _) = fingerprint::read_file(path).map_err(|e| e.to_string())?;
+        let is_compressed = compression::is_compressed(&content);
+        if is_compressed {
+            content = compression::decompress(&content)?;
         }
+        if binary::is_binary(&content) {
+            return Ok(false);
+        }
+        if deserialization::format_version(&decode_text(content)?)? == CURRENT_VERSION {
+            return Ok(false);
+        }
+
+        if is_compressed {
+            let compressed = compression::compress(table.serialize_current().as_bytes())?;
+            file::write_atomic(path, &compressed)?;
+        } else {
+            table.write_file(path)?;
+        }
+        Ok(true)
     }
 
     /// Gets the data saved in the table.
@@ -81,8 +161,82 @@ impl Table {
 
     /// Insert data into the table.
     /// The types of the data must be equal to the data in the table.
-    /// Furthermore, the keys must not already exist in the table
+    /// Furthermore, the keys must not be null and must not already exist in the table
     pub fn insert(&mut self, entry: Vec<Value>) -> Result<(), String> {
+        let new_entry = self.create_entry(entry)?;
+
+        // Check if key already exists
+        for e in &self.entries {
+            if e.key_eq(&new_entry) {
+                return Err("Key already exists".to_string());
+            }
+        }
+
+        // Ok to insert
+        self.entries.push(new_entry);
+
+        Ok(())
+    }
+
+    /// Replace the element with the key values by a new entry, which may also
+    /// change the key.
+    /// The given keys must be in the same order as saved in the table.
+    /// Returns if an element with the key values was found.
+    pub fn update(&mut self, keys: Vec<Value>, entry: Vec<Value>) -> Result<bool, String> {
+        match self.position(&keys) {
+            Some(index) => self.replace(index, entry).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    // The index of the entry with the key values, matched like in `remove`,
+    // so that no entry of a table without key columns is ever found.
+    pub(crate) fn position(&self, keys: &[Value]) -> Option<usize> {
+        let key_columns = self.columns.iter().filter(|c| c.is_key).cloned();
+        if keys.len() != key_columns.clone().count() {
+            return None;
+        }
+        let to_find = Entry::new(key_columns.zip(keys.iter().cloned()).collect());
+
+        self.entries.iter().position(|e| e.key_eq(&to_find))
+    }
+
+    // Replaces the entry at the index, checking the values like on insert.
+    pub(crate) fn replace(&mut self, index: usize, entry: Vec<Value>) -> Result<(), String> {
+        let new_entry = self.create_entry(entry)?;
+
+        // Check if the new key is used by another element
+        for (i, e) in self.entries.iter().enumerate() {
+            if i != index && e.key_eq(&new_entry) {
+                return Err("Key already exists".to_string());
+            }
+        }
+
+        self.entries[index] = new_entry;
+
+        Ok(())
+    }
+
+    // Inserts many entries at once, like `insert` for each entry, but finds
+    // duplicate keys without comparing every pair of entries.
+    pub(crate) fn insert_all(&mut self, entries: Vec<Vec<Value>>) -> Result<(), String> {
+        let has_keys = self.columns.iter().any(|c| c.is_key);
+        let mut keys: HashSet<Vec<Value>> =
+            self.entries.iter().map(|e| e.get_key_values()).collect();
+
+        for entry in entries {
+            let new_entry = self.create_entry(entry)?;
+            if has_keys && !keys.insert(new_entry.get_key_values()) {
+                return Err("Key already exists".to_string());
+            }
+            self.entries.push(new_entry);
+        }
+
+        Ok(())
+    }
+
+    // Checks the values against the columns of the table and creates an entry.
+    pub(crate) fn create_entry(&self, entry: Vec<Value>) -> Result<Entry, String> {
         // Check if all columns are given
         if entry.len() != self.columns.len() {
             return Err("Not all columns are given".to_owned());
@@ -102,36 +256,30 @@ impl Table {
             }
         }
 
+        // Keys identify an element, so they must be known
+        for (value, column) in entry.iter().zip(&self.columns) {
+            if column.is_key && value.is_null() {
+                return Err(format!("Key column {} cannot be null", column.name));
+            }
+        }
+
         // Create new Entry
         let values_iter = entry.into_iter();
         let columns_iter = self.columns.iter().cloned();
 
         let zip_vec: Vec<(Column, Value)> = columns_iter.zip(values_iter).collect();
-        // let values = values_iter.enumerate().map(|(i, v)| (&self.columns[i], v)).collect();
-        let new_entry: Entry = Entry::new(zip_vec);
-
-        // Check if key already exists
-        for e in &self.entries {
-            if e.key_eq(&new_entry) {
-                return Err("Key already exists".to_string());
-            }
-        }
-
-        // Ok to insert
-        self.entries.push(new_entry);
-
-        Ok(())
+        Ok(Entry::new(zip_vec))
     }
 
     /// Remove the element with the key values from the table.
     /// The given keys must be in the same order as saved in the table.
     /// Returns a success value.
     pub fn remove(&mut self, keys: Vec<Value>) -> bool {
-        let key_columns = self.columns.iter().cloned().filter(|c| c.is_key);
+        let key_columns = self.columns.iter().filter(|c| c.is_key).cloned();
         if keys.len() != key_columns.clone().count() {
             return false;
         }
-        let ziped = key_columns.zip(keys.into_iter());
+        let ziped = key_columns.zip(keys);
         let to_remove = Entry::new(ziped.collect());
         let old_entries = self.entries.clone();
         self.entries = self
@@ -164,6 +312,117 @@ impl Table {
 
         Ok(new_table)
     }
+
+    /// Project the columns with the given names, in the given order.
+    /// Key columns keep their key status, so like in `project` entries that
+    /// share the remaining key values are only kept once.
+    /// Will return an error naming the first unknown column.
+    pub fn project_by_names(&self, names: &[&str]) -> Result<Table, String> {
+        let columns = self.columns_by_names(names)?;
+
+        self.project(columns)
+    }
+
+    /// Project the columns with the given names, in the given order.
+    /// All projected columns lose their key status, so every entry of this
+    /// table is kept.
+    /// Will return an error naming the first unknown column.
+    pub fn project_by_names_without_keys(&self, names: &[&str]) -> Result<Table, String> {
+        let columns = self.columns_by_names(names)?;
+        let new_columns = columns
+            .iter()
+            .map(|c| Column {
+                is_key: false,
+                ..c.clone()
+            })
+            .collect();
+
+        let mut new_table = Table::new(new_columns)?;
+
+        for entry in &self.entries {
+            new_table.insert(entry.get_values_in_order(&columns)?)?;
+        }
+
+        Ok(new_table)
+    }
+
+    /// Returns a clone of the table with an additional column computed by the
+    /// given expression. The type of the column is inferred from the expression.
+    pub fn extend<T: 'static + AsRef<str> + Clone>(
+        &self,
+        name: T,
+        expression: Expression,
+    ) -> Result<Table, String> {
+        let column_type = expression.get_type(&self.columns)?;
+        let mut columns = self.columns.clone();
+        columns.push(Column::new(name, column_type));
+
+        let mut new_table = Table::new(columns)?;
+
+        for entry in &self.entries {
+            let mut values = entry.get_values();
+            values.push(expression.evaluate(entry)?);
+            new_table.insert(values)?;
+        }
+
+        Ok(new_table)
+    }
+
+    /// Project the table to columns computed by the given named expressions.
+    /// The types of the columns are inferred from the expressions, none of
+    /// them is a key column.
+    pub fn project_exprs(&self, expressions: &[(&str, Expression)]) -> Result<Table, String> {
+        let mut columns = vec![];
+        for (name, expression) in expressions {
+            columns.push(Column {
+                is_key: false,
+                name: name.to_string(),
+                column_type: expression.get_type(&self.columns)?,
+            });
+        }
+
+        let mut new_table = Table::new(columns)?;
+
+        for entry in &self.entries {
+            let values = expressions
+                .iter()
+                .map(|(_, expression)| expression.evaluate(entry))
+                .collect::<Result<_, _>>()?;
+            new_table.insert(values)?;
+        }
+
+        Ok(new_table)
+    }
+
+    // Looks up the columns with the given names, in the given order.
+    fn columns_by_names(&self, names: &[&str]) -> Result<Vec<Column>, String> {
+        names
+            .iter()
+            .map(|name| {
+                self.columns
+                    .iter()
+                    .find(|c| c.name == *name)
+                    .cloned()
+                    .ok_or_else(|| format!("Unknown column: {}", name))
+            })
+            .collect()
+    }
+}
+
+// Invalid UTF-8 is only replaced if the rows have checksums, which then find
+// the corrupt rows, otherwise values would silently change.
+fn decode_text(content: Vec<u8>) -> Result<String, String> {
+    match String::from_utf8(content) {
+        Ok(text) => Ok(text),
+        Err(e) => {
+            let text = String::from_utf8_lossy(e.as_bytes()).into_owned();
+            if deserialization::format_version(&text)? >= 2 {
+                Ok(text)
+            } else {
+                Err(e.to_string())
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -273,6 +532,68 @@ mod test {
             .is_err());
     }
 
+    #[test]
+    fn null_key_insert() {
+        let column1 = super::Column::key("Test1", crate::types::ColumnType::Integer);
+        let column2 = super::Column::new("Test2", crate::types::ColumnType::String);
+        let columns = vec![column1, column2];
+
+        let mut table = super::Table::new(columns).unwrap();
+
+        assert!(table
+            .insert(vec![
+                crate::values::Value::Null(crate::types::ColumnType::Integer),
+                crate::values::Value::String("Hello".to_string())
+            ])
+            .is_err());
+        assert!(table
+            .insert(vec![
+                crate::values::Value::Integer(10),
+                crate::values::Value::Null(crate::types::ColumnType::String)
+            ])
+            .is_ok());
+    }
+
+    #[test]
+    fn update() {
+        let column1 = super::Column::key("Test1", crate::types::ColumnType::Integer);
+        let column2 = super::Column::new("Test2", crate::types::ColumnType::String);
+        let columns = vec![column1, column2];
+
+        let mut table = super::Table::new(columns).unwrap();
+
+        table.insert(vec![10.into(), "Hello".into()]).unwrap();
+        table.insert(vec![12.into(), "World".into()]).unwrap();
+
+        assert_eq!(
+            table.update(vec![10.into()], vec![11.into(), "Hi".into()]),
+            Ok(true)
+        );
+        assert_eq!(
+            table.update(vec![10.into()], vec![10.into(), "Hi".into()]),
+            Ok(false)
+        );
+        assert!(table
+            .update(vec![11.into()], vec![12.into(), "Hi".into()])
+            .is_err());
+
+        assert_eq!(
+            table.entries[0].get_values(),
+            vec![crate::values::Value::Integer(11), "Hi".into()]
+        );
+        assert_eq!(table.entries.len(), 2);
+    }
+
+    #[test]
+    fn update_without_keys() {
+        let column = super::Column::new("Test", crate::types::ColumnType::Integer);
+        let mut table = super::Table::new(vec![column]).unwrap();
+        table.insert(vec![10.into()]).unwrap();
+
+        assert_eq!(table.update(vec![], vec![11.into()]), Ok(false));
+        assert_eq!(table.entries[0].get_values(), vec![10.into()]);
+    }
+
     #[test]
     fn normal_remove() {
         let column1 = super::Column::key("Test1", crate::types::ColumnType::Integer);
@@ -372,12 +693,12 @@ mod test {
         let table_sub1_values: Vec<i32> = table_sub1
             .get_entries()
             .iter()
-            .map(|e| i32::try_from(e.get_values().get(0).unwrap().clone()).unwrap())
+            .map(|e| i32::try_from(e.get_values().first().unwrap().clone()).unwrap())
             .collect();
         let table_sub2_values: Vec<String> = table_sub2
             .get_entries()
             .iter()
-            .map(|e| String::try_from(e.get_values().get(0).unwrap().clone()).unwrap())
+            .map(|e| String::try_from(e.get_values().first().unwrap().clone()).unwrap())
             .collect();
 
         println!("{}", table);
@@ -386,4 +707,225 @@ mod test {
         assert!(table_sub1_values == vec![10, 12]);
         assert!(table_sub2_values == vec!["Hello".to_string(), "World".to_string()]);
     }
+
+    fn create_people_table() -> super::Table {
+        let column1 = super::Column::key("First Name", crate::types::ColumnType::String);
+        let column2 = super::Column::key("Last Name", crate::types::ColumnType::String);
+        let column3 = super::Column::new("Age", crate::types::ColumnType::Integer);
+        let mut table = super::Table::new(vec![column1, column2, column3]).unwrap();
+
+        table
+            .insert(vec!["Peter".into(), "Pan".into(), 15.into()])
+            .unwrap();
+        table
+            .insert(vec!["Wendy".into(), "Pan".into(), 15.into()])
+            .unwrap();
+
+        table
+    }
+
+    #[test]
+    fn project_by_names() {
+        let table = create_people_table();
+
+        let projected = table.project_by_names(&["Age", "Last Name"]).unwrap();
+
+        assert_eq!(
+            projected.columns,
+            vec![
+                super::Column::new("Age", crate::types::ColumnType::Integer),
+                super::Column::key("Last Name", crate::types::ColumnType::String)
+            ]
+        );
+        // Both entries have the same remaining key
+        assert_eq!(projected.entries.len(), 1);
+        assert_eq!(
+            projected.entries[0].get_values(),
+            vec![crate::values::Value::Integer(15), "Pan".into()]
+        );
+    }
+
+    #[test]
+    fn project_by_names_without_keys() {
+        let table = create_people_table();
+
+        let projected = table
+            .project_by_names_without_keys(&["Last Name", "First Name"])
+            .unwrap();
+
+        assert_eq!(
+            projected.columns,
+            vec![
+                super::Column::new("Last Name", crate::types::ColumnType::String),
+                super::Column::new("First Name", crate::types::ColumnType::String)
+            ]
+        );
+        assert_eq!(projected.entries.len(), 2);
+        assert_eq!(
+            projected.entries[1].get_values(),
+            vec!["Pan".into(), "Wendy".into()]
+        );
+    }
+
+    #[test]
+    fn project_by_unknown_name() {
+        let table = create_people_table();
+
+        assert_eq!(
+            table.project_by_names(&["Age", "Height"]),
+            Err("Unknown column: Height".to_string())
+        );
+        assert!(table.project_by_names_without_keys(&["Name"]).is_err());
+    }
+
+    #[test]
+    fn extend() {
+        let table = create_people_table();
+
+        let extended = table
+            .extend(
+                "Age in months",
+                super::Expression::column("Age") * super::Expression::literal(12),
+            )
+            .unwrap();
+
+        assert_eq!(
+            extended.columns[3],
+            super::Column::new("Age in months", crate::types::ColumnType::Integer)
+        );
+        assert_eq!(
+            extended.entries[0].get_values(),
+            vec!["Peter".into(), "Pan".into(), 15.into(), 180.into()]
+        );
+        assert!(table.extend("Age", super::Expression::literal(0)).is_err());
+    }
+
+    #[test]
+    fn project_exprs() {
+        let table = create_people_table();
+
+        let full_name = super::Expression::column("First Name")
+            .concat(super::Expression::literal(" "))
+            .concat(super::Expression::column("Last Name"));
+        let projected = table
+            .project_exprs(&[
+                ("Full Name", full_name),
+                ("Age", super::Expression::column("Age")),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            projected.columns,
+            vec![
+                super::Column::new("Full Name", crate::types::ColumnType::String),
+                super::Column::new("Age", crate::types::ColumnType::Integer)
+            ]
+        );
+        assert_eq!(
+            projected.entries[1].get_values(),
+            vec!["Wendy Pan".into(), 15.into()]
+        );
+        assert!(table
+            .project_exprs(&[(
+                "Wrong",
+                super::Expression::column("Age").concat(super::Expression::column("Height"))
+            )])
+            .is_err());
+    }
+
+    #[test]
+    fn write_smaller_file() {
+        let path = std::env::temp_dir().join(format!(
+            "file-minidb-table-shrink-{}.table",
+            std::process::id()
+        ));
+        let mut table = create_people_table();
+        table
+            .insert(vec!["John".into(), "Darling".into(), 10.into()])
+            .unwrap();
+        table.write_file(&path).unwrap();
+
+        assert!(table.remove(vec!["John".into(), "Darling".into()]));
+        assert!(table.remove(vec!["Wendy".into(), "Pan".into()]));
+        table.write_file(&path).unwrap();
+
+        assert_eq!(super::Table::from_file(&path), Ok(table));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn write_file_error() {
+        let path = std::env::temp_dir()
+            .join(format!("file-minidb-missing-{}", std::process::id()))
+            .join("people.table");
+
+        assert!(create_people_table().write_file(&path).is_err());
+        assert!(!path.with_file_name("people.table.tmp").exists());
+    }
+
+    #[test]
+    fn migrate_file() {
+        let path = std::env::temp_dir().join(format!(
+            "file-minidb-table-migrate-{}.table",
+            std::process::id()
+        ));
+        let table = create_people_table();
+
+        for version in 0..crate::serializer::CURRENT_VERSION {
+            table.write_file_version(&path, version).unwrap();
+            assert_eq!(super::Table::migrate_file(&path), Ok(true));
+            assert!(std::fs::read_to_string(&path)
+                .unwrap()
+                .starts_with("#file-minidb version=2 entries=2\n"));
+            assert_eq!(super::Table::from_file(&path), Ok(table.clone()));
+
+            assert_eq!(super::Table::migrate_file(&path), Ok(false));
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn from_file_corrupt_byte() {
+        let path = std::env::temp_dir().join(format!(
+            "file-minidb-table-corrupt-{}.table",
+            std::process::id()
+        ));
+        let mut table = create_people_table();
+        table.write_file(&path).unwrap();
+
+        // Replace a byte of the last row with invalid UTF-8
+        let mut content = std::fs::read(&path).unwrap();
+        let position = content.len() - 3;
+        content[position] = 0xff;
+        std::fs::write(&path, content).unwrap();
+
+        let error = super::Table::from_file(&path).unwrap_err();
+        assert!(error.contains("lines 4 (Checksum mismatch)"), "{}", error);
+
+        let (loaded, corrupt) = super::Table::from_file_lenient(&path).unwrap();
+        assert_eq!(corrupt.len(), 1);
+        assert!(table.remove(vec!["Wendy".into(), "Pan".into()]));
+        assert_eq!(loaded, table);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn from_compressed_file_without_feature() {
+        let path = std::env::temp_dir().join(format!(
+            "file-minidb-table-gzip-{}.table",
+            std::process::id()
+        ));
+        std::fs::write(&path, [0x1f, 0x8b, 8, 0]).unwrap();
+
+        assert_eq!(
+            super::Table::from_file(&path),
+            Err("Reading compressed tables needs the compression feature".to_string())
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,20 @@
+// Data shared by the tests and the benchmarks, which include this file with
+// `#[path]`, so it only refers to the crate as `file_minidb`.
+
+use file_minidb::table::Table;
+
+/// A table of people with the number of entries.
+/// Built from its text form, as inserting one by one checks every key.
+pub fn create_large_table(rows: usize) -> Table {
+    let mut text = String::from(r#"key int "Id",str "Name",int "Age""#);
+    text.push('\n');
+    for i in 0..rows {
+        text.push_str(&format!(
+            "\"{}\",\"Person\\, number {}\",\"{}\"\n",
+            i,
+            i,
+            i % 100
+        ));
+    }
+    Table::deserialize(text).unwrap()
+}
@@ -0,0 +1,209 @@
+use crate::row::Row;
+use crate::serializer::Serializable;
+use crate::table::Table;
+use crate::values::Value;
+
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// A table whose entries are rows of type `T`, with the columns of `T::schema()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypedTable<T: Row> {
+    table: Table,
+    row: PhantomData<T>,
+}
+
+impl<T: Row> TypedTable<T> {
+    /// Creates an empty table with the schema of the rows.
+    pub fn new() -> Result<TypedTable<T>, String> {
+        Ok(TypedTable {
+            table: Table::new(T::schema())?,
+            row: PhantomData,
+        })
+    }
+
+    /// Wraps the table. Will error if it does not have the schema of the rows.
+    pub fn from_table(table: Table) -> Result<TypedTable<T>, String> {
+        let schema = T::schema();
+        if table.columns != schema {
+            return Err(format!(
+                "Schema mismatch, expected {}, found {}",
+                schema.serialize(),
+                table.columns.serialize()
+            ));
+        }
+
+        Ok(TypedTable {
+            table,
+            row: PhantomData,
+        })
+    }
+
+    /// Loads the table file, see `Table::from_file`.
+    /// Will error if the table does not have the schema of the rows.
+    pub fn from_file(path: &Path) -> Result<TypedTable<T>, String> {
+        TypedTable::from_table(Table::from_file(path)?)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Writes the table to the file, see `Table::write_file`.
+    pub fn write_file(&self, path: &Path) -> Result<(), String> {
+        self.table.write_file(path)
+    }
+
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn into_table(self) -> Table {
+        self.table
+    }
+
+    /// Insert a row, like `Table::insert`.
+    pub fn insert(&mut self, row: &T) -> Result<(), String> {
+        self.table.insert(row.to_values())
+    }
+
+    /// The row with the key values, in the order of the key columns.
+    pub fn get(&self, keys: Vec<Value>) -> Result<Option<T>, String> {
+        self.table
+            .position(&keys)
+            .map(|index| T::from_entry(&self.table.entries[index]))
+            .transpose()
+    }
+
+    /// Remove the row with the key values, like `Table::remove`.
+    pub fn remove(&mut self, keys: Vec<Value>) -> bool {
+        self.table.remove(keys)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Result<T, String>> + '_ {
+        self.table.iter_as()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::TypedTable;
+    use crate::column::Column;
+    use crate::row::{ColumnValue, Row};
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    use std::fs;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Person {
+        name: String,
+        age: Option<i32>,
+    }
+
+    impl Row for Person {
+        fn schema() -> Vec<Column> {
+            vec![
+                Column::key("Name", ColumnType::String),
+                Column::new("Age", ColumnType::Integer),
+            ]
+        }
+
+        fn to_values(&self) -> Vec<Value> {
+            vec![self.name.to_value(), self.age.to_value()]
+        }
+
+        fn from_values(values: Vec<Value>) -> Result<Self, String> {
+            let mut values = values.into_iter();
+            Ok(Person {
+                name: ColumnValue::from_value(values.next().unwrap())?,
+                age: ColumnValue::from_value(values.next().unwrap())?,
+            })
+        }
+    }
+
+    // A row without key columns.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Note {
+        text: String,
+    }
+
+    impl Row for Note {
+        fn schema() -> Vec<Column> {
+            vec![Column::new("Text", ColumnType::String)]
+        }
+
+        fn to_values(&self) -> Vec<Value> {
+            vec![self.text.to_value()]
+        }
+
+        fn from_values(values: Vec<Value>) -> Result<Self, String> {
+            Ok(Note {
+                text: ColumnValue::from_value(values.into_iter().next().unwrap())?,
+            })
+        }
+    }
+
+    fn person(name: &str, age: Option<i32>) -> Person {
+        Person {
+            name: name.to_string(),
+            age,
+        }
+    }
+
+    #[test]
+    fn rows() {
+        let mut table = TypedTable::<Person>::new().unwrap();
+        table.insert(&person("Peter", Some(15))).unwrap();
+        table.insert(&person("Wendy", None)).unwrap();
+        assert!(table.insert(&person("Peter", None)).is_err());
+
+        assert_eq!(
+            table.get(vec!["Wendy".into()]),
+            Ok(Some(person("Wendy", None)))
+        );
+        assert_eq!(table.get(vec!["John".into()]), Ok(None));
+
+        assert!(table.remove(vec!["Peter".into()]));
+        let rows: Result<Vec<Person>, String> = table.iter().collect();
+        assert_eq!(rows, Ok(vec![person("Wendy", None)]));
+    }
+
+    #[test]
+    fn rows_without_keys() {
+        let mut table = TypedTable::<Note>::new().unwrap();
+        table
+            .insert(&Note {
+                text: "Hello".to_string(),
+            })
+            .unwrap();
+
+        assert_eq!(table.get(vec![]), Ok(None));
+    }
+
+    #[test]
+    fn from_file() {
+        let path =
+            std::env::temp_dir().join(format!("file-minidb-typed-{}.table", std::process::id()));
+        let mut table = TypedTable::<Person>::new().unwrap();
+        table.insert(&person("Peter", Some(15))).unwrap();
+        table.write_file(&path).unwrap();
+        assert_eq!(TypedTable::<Person>::from_file(&path), Ok(table));
+
+        Table::new(vec![
+            Column::key("Name", ColumnType::String),
+            Column::new("Age", ColumnType::String),
+        ])
+        .unwrap()
+        .write_file(&path)
+        .unwrap();
+        assert_eq!(
+            TypedTable::<Person>::from_file(&path),
+            Err(format!(
+                "{}: Schema mismatch, expected key str \"Name\",int \"Age\", found key str \"Name\",str \"Age\"",
+                path.display()
+            ))
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -3,10 +3,14 @@ use crate::types::ColumnType;
 use std::convert::TryFrom;
 use std::fmt;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     String(String),
     Integer(i32),
+    /// The absence of a value in a column of the given type.
+    /// Null values can not be stored in key columns.
+    Null(ColumnType),
 }
 
 impl fmt::Display for Value {
@@ -14,6 +18,7 @@ impl fmt::Display for Value {
         match &self {
             Value::String(v) => write!(f, "{}", v),
             Value::Integer(v) => write!(f, "{}", v),
+            Value::Null(_) => write!(f, "null"),
         }
     }
 }
@@ -23,8 +28,13 @@ impl Value {
         match &self {
             Value::String(_) => ColumnType::String,
             Value::Integer(_) => ColumnType::Integer,
+            Value::Null(t) => t.clone(),
         }
     }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null(_))
+    }
 }
 
 impl From<String> for Value {
@@ -49,10 +59,10 @@ impl TryFrom<Value> for i32 {
     type Error = &'static str;
 
     fn try_from(value: Value) -> Result<Self, Self::Error> {
-        if let Value::Integer(i) = value {
-            Ok(i)
-        } else {
-            Err("Cannot convert from String")
+        match value {
+            Value::Integer(i) => Ok(i),
+            Value::String(_) => Err("Cannot convert from String"),
+            Value::Null(_) => Err("Cannot convert from null"),
         }
     }
 }
@@ -61,10 +71,10 @@ impl TryFrom<Value> for String {
     type Error = &'static str;
 
     fn try_from(value: Value) -> Result<Self, Self::Error> {
-        if let Value::String(str) = value {
-            Ok(str)
-        } else {
-            Err("Cannot convert from Integer")
+        match value {
+            Value::String(str) => Ok(str),
+            Value::Integer(_) => Err("Cannot convert from Integer"),
+            Value::Null(_) => Err("Cannot convert from null"),
         }
     }
 }
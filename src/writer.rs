@@ -0,0 +1,170 @@
+use crate::column::Column;
+use crate::file;
+use crate::serializer::{with_checksum, Serializable, CURRENT_VERSION, HEADER_PREFIX};
+use crate::table::Table;
+use crate::values::Value;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes a table file one entry at a time, without holding the whole table
+/// in memory, in the current version of the text format.
+///
+/// The entries are written to a temporary file next to the table file, which
+/// replaces the table file on `finish`, like `Table::write_file`. Dropping the
+/// writer without finishing removes the temporary file.
+///
+/// Entries are checked like `Table::insert`, except for duplicate keys, as
+/// that would need all keys in memory. Such a file fails to load.
+#[derive(Debug)]
+pub struct TableWriter {
+    // Only has the columns, to check entries.
+    table: Table,
+    path: PathBuf,
+    temporary: PathBuf,
+    // None once finished.
+    writer: Option<BufWriter<File>>,
+}
+
+impl TableWriter {
+    /// Starts writing a table with the columns to the file.
+    pub fn create(path: &Path, columns: Vec<Column>) -> Result<TableWriter, String> {
+        let table = Table::new(columns)?;
+        let temporary = file::temporary_path(path)?;
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temporary)
+            .map_err(|e| e.to_string())?;
+
+        let mut writer = TableWriter {
+            table,
+            path: path.to_path_buf(),
+            temporary,
+            writer: Some(BufWriter::new(file)),
+        };
+        // The number of entries is not known, so the header has none.
+        let header = format!("{} version={}\n", HEADER_PREFIX, CURRENT_VERSION);
+        writer.write(header.as_bytes())?;
+        let columns = writer.table.columns.serialize();
+        writer.write(columns.as_bytes())?;
+        writer.write(b"\n")?;
+
+        Ok(writer)
+    }
+
+    /// Appends an entry to the file.
+    pub fn append(&mut self, entry: Vec<Value>) -> Result<(), String> {
+        let row = with_checksum(self.table.create_entry(entry)?.serialize());
+        self.write(row.as_bytes())?;
+        self.write(b"\n")
+    }
+
+    /// Writes the remaining entries and atomically replaces the table file.
+    pub fn finish(mut self) -> Result<(), String> {
+        let file = self
+            .writer
+            .take()
+            .unwrap()
+            .into_inner()
+            .map_err(|e| e.to_string())?;
+        let result = file
+            .sync_all()
+            .and_then(|_| fs::rename(&self.temporary, &self.path))
+            .map_err(|e| e.to_string());
+        if result.is_err() {
+            let _ = fs::remove_file(&self.temporary);
+            return result;
+        }
+
+        file::sync_directory(&self.path)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.writer
+            .as_mut()
+            .unwrap()
+            .write_all(bytes)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Drop for TableWriter {
+    fn drop(&mut self) {
+        if self.writer.take().is_some() {
+            let _ = fs::remove_file(&self.temporary);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::TableWriter;
+    use crate::column::Column;
+    use crate::serializer::Serializable;
+    use crate::table::Table;
+    use crate::types::ColumnType;
+    use crate::values::Value;
+
+    use std::fs;
+
+    fn columns() -> Vec<Column> {
+        vec![
+            Column::key("Name", ColumnType::String),
+            Column::new("Age", ColumnType::Integer),
+        ]
+    }
+
+    #[test]
+    fn write_entries() {
+        let path =
+            std::env::temp_dir().join(format!("file-minidb-writer-{}.table", std::process::id()));
+        let mut expected = Table::new(columns()).unwrap();
+
+        let mut writer = TableWriter::create(&path, columns()).unwrap();
+        for i in 0..1000 {
+            let entry = vec![format!("Person, {}", i).into(), Value::from(i % 100)];
+            writer.append(entry.clone()).unwrap();
+            expected.insert(entry).unwrap();
+        }
+        assert!(writer.append(vec![1.into(), 2.into()]).is_err());
+        assert!(!path.exists());
+        writer.finish().unwrap();
+
+        assert_eq!(Table::from_file(&path), Ok(expected));
+
+        let writer = TableWriter::create(&path, columns()).unwrap();
+        drop(writer);
+        assert!(Table::from_file(&path).is_ok());
+        assert!(!path.with_extension("table.tmp").exists());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn serialize_into() {
+        let mut table = Table::new(columns()).unwrap();
+        table.insert(vec!["Peter".into(), 15.into()]).unwrap();
+        table
+            .insert(vec!["Wendy".into(), Value::Null(ColumnType::Integer)])
+            .unwrap();
+
+        let mut result = vec![];
+        table.serialize_into(&mut result).unwrap();
+        assert_eq!(String::from_utf8(result).unwrap(), table.serialize());
+
+        let mut result = vec![];
+        table.write_current_into(&mut result).unwrap();
+        assert_eq!(
+            String::from_utf8(result).unwrap(),
+            table.serialize_current()
+        );
+
+        let mut result = vec![];
+        table.get_entries()[0].serialize_into(&mut result).unwrap();
+        assert_eq!(result, br#""Peter","15""#);
+    }
+}